@@ -0,0 +1,78 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use mongodb::bson::Document;
+use serde_json::json;
+use std::sync::Arc;
+use crate::core::plans::Plan;
+use crate::core::rbac::USER_ROLES;
+use crate::db::AppState;
+use crate::models::user::AdminUpdateUserRequest;
+
+// --- GET /admin/users ---
+pub async fn list_users(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match state.user_repo.list_all().await {
+        Ok(users) => {
+            let data: Vec<_> = users.into_iter().map(|u| json!({
+                "id": u.id.map(|oid| oid.to_hex()).unwrap_or_default(),
+                "name": u.name,
+                "email": u.email,
+                "plan": u.plan,
+                "role": u.role,
+                "email_verified": u.email_verified,
+            })).collect();
+            (StatusCode::OK, Json(json!({ "status": "success", "data": data }))).into_response()
+        }
+        Err(e) => {
+            eprintln!("Database Error: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "Database Error" }))).into_response()
+        }
+    }
+}
+
+// --- PATCH /admin/users/:id: ubah role dan/atau plan ---
+pub async fn update_user(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+    Json(payload): Json<AdminUpdateUserRequest>,
+) -> impl IntoResponse {
+    let mut fields = Document::new();
+
+    if let Some(role) = payload.role {
+        if !USER_ROLES.contains(&role.as_str()) {
+            return (StatusCode::BAD_REQUEST, Json(json!({
+                "error": format!("Unknown role '{}'", role),
+                "allowed": USER_ROLES,
+            }))).into_response();
+        }
+        fields.insert("role", role);
+    }
+
+    if let Some(plan) = payload.plan {
+        let parsed = Plan::parse(&plan);
+        if parsed.as_str() != plan.to_lowercase() {
+            return (StatusCode::BAD_REQUEST, Json(json!({
+                "error": format!("Unknown plan '{}'", plan),
+                "allowed": ["basic", "pro", "enterprise"],
+            }))).into_response();
+        }
+        fields.insert("plan", parsed.as_str());
+    }
+
+    if fields.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Nothing to update" }))).into_response();
+    }
+
+    match state.user_repo.update_fields(&user_id, fields.clone()).await {
+        Ok(count) if count > 0 => (StatusCode::OK, Json(json!({
+            "status": "success",
+            "user_id": user_id,
+            "updated": fields,
+        }))).into_response(),
+        Ok(_) => (StatusCode::NOT_FOUND, Json(json!({ "error": "User not found" }))).into_response(),
+        Err(_) => (StatusCode::BAD_REQUEST, Json(json!({ "error": "Invalid ID format" }))).into_response(),
+    }
+}
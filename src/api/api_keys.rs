@@ -0,0 +1,119 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use serde_json::json;
+use std::sync::Arc;
+use crate::core::auth_utils::{generate_opaque_token, hash_token};
+use crate::core::current_user::CurrentUser;
+use crate::db::AppState;
+use crate::models::api_key::{ApiKey, CreateApiKeyRequest, ALL_SCOPES};
+
+const KEY_PREFIX: &str = "kp_";
+
+// --- GET /api-keys ---
+pub async fn list_api_keys(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+
+    match state.api_key_repo.list_by_user(&current.id).await {
+        Ok(keys) => {
+            let data: Vec<_> = keys.into_iter().map(|k| json!({
+                "id": k.id.map(|oid| oid.to_hex()).unwrap_or_default(),
+                "name": k.name,
+                "prefix": k.prefix,
+                "scopes": k.scopes,
+                "created_at": k.created_at,
+                "last_used_at": k.last_used_at.map(|d| d.to_chrono()),
+            })).collect();
+
+            (StatusCode::OK, Json(json!({ "status": "success", "data": data }))).into_response()
+        }
+        Err(e) => {
+            eprintln!("Database Error: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "Failed fetch" }))).into_response()
+        }
+    }
+}
+
+// --- POST /api-keys: key asli hanya dikembalikan sekali di sini ---
+pub async fn create_api_key(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+
+    let name = payload.name.trim().to_string();
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Name is required" }))).into_response();
+    }
+
+    // Tanpa scopes = semua scope
+    let scopes = payload.scopes.unwrap_or_else(|| ALL_SCOPES.iter().map(|s| s.to_string()).collect());
+    if scopes.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "At least one scope is required" }))).into_response();
+    }
+    if let Some(unknown) = scopes.iter().find(|s| !ALL_SCOPES.contains(&s.as_str())) {
+        return (StatusCode::BAD_REQUEST, Json(json!({
+            "error": format!("Unknown scope '{}'", unknown),
+            "allowed": ALL_SCOPES,
+        }))).into_response();
+    }
+
+    let raw_key = format!("{}{}", KEY_PREFIX, generate_opaque_token());
+    let api_key = ApiKey {
+        id: None,
+        user_id: current.id.clone(),
+        name: name.clone(),
+        prefix: raw_key.chars().take(KEY_PREFIX.len() + 8).collect(),
+        key_hash: hash_token(&raw_key),
+        scopes: scopes.clone(),
+        revoked: false,
+        created_at: Utc::now(),
+        last_used_at: None,
+    };
+
+    match state.api_key_repo.create(api_key).await {
+        Ok(id) => (StatusCode::CREATED, Json(json!({
+            "status": "success",
+            "id": id,
+            "name": name,
+            "scopes": scopes,
+            "key": raw_key,
+            "message": "Store this key now, it will not be shown again"
+        }))).into_response(),
+        Err(e) => {
+            eprintln!("Database Error: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "Database Error" }))).into_response()
+        }
+    }
+}
+
+// --- DELETE /api-keys/:id ---
+pub async fn revoke_api_key(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+
+    match state.api_key_repo.revoke(&id, &current.id).await {
+        Ok(count) if count > 0 => {
+            (StatusCode::OK, Json(json!({ "status": "success", "id": id }))).into_response()
+        }
+        Ok(_) => (StatusCode::NOT_FOUND, Json(json!({ "error": "API key not found" }))).into_response(),
+        Err(_) => (StatusCode::BAD_REQUEST, Json(json!({ "error": "Invalid ID format" }))).into_response(),
+    }
+}
@@ -1,16 +1,176 @@
 use axum::{
-    extract::State, 
-    http::StatusCode, 
-    response::IntoResponse, 
+    extract::{ConnectInfo, State}, 
+    http::{header::USER_AGENT, HeaderMap, StatusCode}, 
+    response::{IntoResponse, Response}, 
     Json
 };
-use std::sync::Arc;
-use bcrypt::{hash, verify, DEFAULT_COST};
+use std::{net::{IpAddr, SocketAddr}, sync::{Arc, OnceLock}};
+use bcrypt::{hash, DEFAULT_COST};
+use chrono::{Duration, Utc};
 use tower_cookies::{Cookies, Cookie};
+use crate::core::auth_utils::{
+    create_token, generate_opaque_token, hash_token, verify_token,
+    ACCESS_TOKEN_TTL_MINUTES, REFRESH_COOKIE, REFRESH_COOKIE_PATH, REFRESH_TOKEN_TTL_DAYS, SESSION_COOKIE,
+};
+use crate::core::current_user::CurrentUser;
+use crate::core::login_guard;
+use crate::core::plans;
+use crate::core::rbac::ROLE_ANALYST;
 use crate::db::AppState;
-use crate::models::user::{User, AuthRequest};
+use crate::api::two_factor::verify_second_factor;
+use crate::models::auth_token::{AuthToken, PURPOSE_MFA_LOGIN, PURPOSE_RESET_PASSWORD, PURPOSE_VERIFY_EMAIL};
+use crate::models::session::Session;
+use crate::models::user::{User, AuthRequest, ForgotPasswordRequest, MfaLoginRequest, ResetPasswordRequest, TokenRequest};
+use crate::repository::is_duplicate_key;
 use serde_json::json;
 
+// Proxy tepercaya dari env TRUSTED_PROXIES (IP dipisah koma). Kosong = X-Forwarded-For diabaikan.
+fn trusted_proxies() -> &'static [IpAddr] {
+    static PROXIES: OnceLock<Vec<IpAddr>> = OnceLock::new();
+    PROXIES.get_or_init(|| {
+        std::env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|ip| ip.trim().parse().ok())
+            .collect()
+    })
+}
+
+// IP client untuk sesi & lockout per IP. X-Forwarded-For hanya dipakai jika koneksi datang dari
+// proxy tepercaya; dibaca dari kanan, melewati hop proxy tepercaya, karena nilai paling kiri bisa dipalsukan client.
+pub(crate) fn client_ip(headers: &HeaderMap, addr: SocketAddr) -> String {
+    let proxies = trusted_proxies();
+    if !proxies.contains(&addr.ip()) {
+        return addr.ip().to_string();
+    }
+
+    let forwarded: Vec<IpAddr> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|v| v.trim().parse().ok())
+        .collect();
+    forwarded
+        .iter()
+        .rev()
+        .find(|ip| !proxies.contains(ip))
+        .or(forwarded.first())
+        .unwrap_or(&addr.ip())
+        .to_string()
+}
+
+pub(crate) fn user_agent(headers: &HeaderMap) -> String {
+    headers
+        .get(USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+const VERIFY_EMAIL_TTL_HOURS: i64 = 24;
+const RESET_PASSWORD_TTL_MINUTES: i64 = 60;
+const MFA_LOGIN_TTL_MINUTES: i64 = 5;
+
+// Buat token sekali pakai baru (token lama dengan tujuan sama dibatalkan).
+// Yang dikembalikan adalah token mentah untuk dikirim via email.
+async fn issue_auth_token(state: &AppState, user_id: &str, purpose: &str, ttl: Duration) -> mongodb::error::Result<String> {
+    state.auth_token_repo.invalidate_for_user(user_id, purpose).await?;
+
+    let token = generate_opaque_token();
+    let now = Utc::now();
+    state.auth_token_repo.create(AuthToken {
+        id: None,
+        user_id: user_id.to_string(),
+        purpose: purpose.to_string(),
+        token_hash: hash_token(&token),
+        used: false,
+        created_at: now,
+        expires_at: now + ttl,
+    }).await?;
+
+    Ok(token)
+}
+
+// Dipakai saat register dan saat user mengganti email
+pub(crate) async fn send_verification_email(state: &AppState, user_id: &str, email: &str) {
+    match issue_auth_token(state, user_id, PURPOSE_VERIFY_EMAIL, Duration::hours(VERIFY_EMAIL_TTL_HOURS)).await {
+        Ok(token) => {
+            let link = format!("{}/verify-email?token={}", state.app_base_url, token);
+            let body = format!("Halo,\n\nKlik link berikut untuk verifikasi email akun Kepin Anda:\n{}\n\nLink berlaku {} jam.", link, VERIFY_EMAIL_TTL_HOURS);
+            if let Err(e) = state.mailer.send(email, "Verifikasi Email Kepin", &body).await {
+                eprintln!("Mailer Error: {}", e);
+            }
+        }
+        Err(e) => eprintln!("Auth Token Error: {}", e),
+    }
+}
+
+fn set_auth_cookies(cookies: &Cookies, access_token: String, refresh_token: String) {
+    let mut access = Cookie::new(SESSION_COOKIE, access_token);
+    access.set_path("/");
+    access.set_http_only(true);
+    access.set_same_site(tower_cookies::cookie::SameSite::Lax);
+    access.set_max_age(tower_cookies::cookie::time::Duration::minutes(ACCESS_TOKEN_TTL_MINUTES));
+    cookies.add(access);
+
+    let mut refresh = Cookie::new(REFRESH_COOKIE, refresh_token);
+    refresh.set_path(REFRESH_COOKIE_PATH);
+    refresh.set_http_only(true);
+    refresh.set_same_site(tower_cookies::cookie::SameSite::Strict);
+    refresh.set_max_age(tower_cookies::cookie::time::Duration::days(REFRESH_TOKEN_TTL_DAYS));
+    cookies.add(refresh);
+}
+
+pub(crate) fn clear_auth_cookies(cookies: &Cookies) {
+    let mut access = Cookie::new(SESSION_COOKIE, "");
+    access.set_path("/");
+    cookies.remove(access);
+
+    let mut refresh = Cookie::new(REFRESH_COOKIE, "");
+    refresh.set_path(REFRESH_COOKIE_PATH);
+    cookies.remove(refresh);
+}
+
+// Membuat record sesi baru + set cookie access/refresh token.
+// Dipakai oleh semua jalur login.
+pub(crate) async fn start_session(
+    state: &AppState,
+    cookies: &Cookies,
+    user_id: &str,
+    ip: String,
+    user_agent: String,
+) -> Result<(), Response> {
+    let refresh_token = generate_opaque_token();
+    let now = Utc::now();
+
+    let session = Session {
+        id: None,
+        user_id: user_id.to_string(),
+        refresh_token_hash: hash_token(&refresh_token),
+        user_agent,
+        ip,
+        revoked: false,
+        created_at: now,
+        last_used_at: now,
+        expires_at: now + Duration::days(REFRESH_TOKEN_TTL_DAYS),
+    };
+
+    let session_id = state.session_repo.create(session).await.map_err(|e| {
+        eprintln!("Session Error: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database Error").into_response()
+    })?;
+
+    // Cookie berisi JWT yang ditandatangani, bukan email mentah
+    let access_token = create_token(user_id, &session_id, &state.jwt_secret).map_err(|e| {
+        eprintln!("JWT Error: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Token Error").into_response()
+    })?;
+
+    set_auth_cookies(cookies, access_token, refresh_token);
+    Ok(())
+}
+
 pub async fn register(
     State(state): State<Arc<AppState>>, 
     Json(payload): Json<AuthRequest>
@@ -31,69 +191,320 @@ pub async fn register(
         name: payload.name.unwrap_or_default(),
         password: hashed_password,
         plan: "basic".to_string(),
+        email_verified: false,
+        role: ROLE_ANALYST.to_string(),
+        totp_secret: None,
+        totp_enabled: false,
+        totp_last_step: None,
+        recovery_codes: Vec::new(),
+        avatar_url: None,
     };
+    let email = new_user.email.clone();
 
     // 3. Save
-    match state.user_repo.create_user(new_user).await {
-        Ok(_) => (StatusCode::CREATED, "Register Successfuly").into_response(),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Database Error").into_response(),
-    }
+    let user_id = match state.user_repo.create_user(new_user).await {
+        Ok(id) => id,
+        // Register paralel dengan email yang sama: ditolak unique index
+        Err(e) if is_duplicate_key(&e) => return (StatusCode::CONFLICT, "Email already exists").into_response(),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Database Error").into_response(),
+    };
+
+    // 4. Kirim email verifikasi (gagal kirim tidak membatalkan registrasi)
+    send_verification_email(&state, &user_id, &email).await;
+
+    (StatusCode::CREATED, "Register Successfuly, please verify your email").into_response()
 }
 
 pub async fn login(
     State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     cookies: Cookies,
     Json(payload): Json<AuthRequest>,
 ) -> impl IntoResponse {
-    let user = match state.user_repo.find_by_email(&payload.email).await {
-        Some(u) => u,
-        None => return (StatusCode::UNAUTHORIZED, "Invalid credentials").into_response(),
-    };
+    let ip = client_ip(&headers, addr);
+    if let Err(resp) = login_guard::ensure_not_throttled(&state, &[login_guard::account_key(&payload.email), login_guard::ip_key(&ip)]).await {
+        return resp;
+    }
+
+    // Email tidak terdaftar tetap melewati bcrypt (hash dummy) agar tidak bisa dibedakan dari timing
+    let user = state.user_repo.find_by_email(&payload.email).await;
+    let password_hash = user.as_ref().map(|u| u.password.clone());
+    let password = payload.password.clone();
 
     let is_valid = tokio::task::spawn_blocking(move || {
-        verify(payload.password, &user.password).unwrap_or(false)
+        login_guard::verify_password(&password, password_hash.as_deref())
     }).await.unwrap();
 
-    if !is_valid {
-        return (StatusCode::UNAUTHORIZED, "Invalid credentials").into_response();
+    let user = match user {
+        Some(u) if is_valid => u,
+        _ => {
+            login_guard::record_failure(&state, &payload.email, &ip).await;
+            return (StatusCode::UNAUTHORIZED, "Invalid credentials").into_response();
+        }
+    };
+    // Dengan 2FA, counter akun baru di-reset setelah kode benar di /auth/login/2fa
+    if !user.totp_enabled {
+        login_guard::record_success(&state, &payload.email).await;
+    }
+
+    let user_id = match user.id {
+        Some(oid) => oid.to_hex(),
+        None => return (StatusCode::INTERNAL_SERVER_ERROR, "User has no ID").into_response(),
+    };
+    let email_verified = user.email_verified;
+
+    if !email_verified {
+        return (StatusCode::FORBIDDEN, "Email not verified").into_response();
+    }
+
+    // 2FA aktif: sesi belum dibuat, client harus lanjut ke /auth/login/2fa dengan mfa_token
+    if user.totp_enabled {
+        return match issue_auth_token(&state, &user_id, PURPOSE_MFA_LOGIN, Duration::minutes(MFA_LOGIN_TTL_MINUTES)).await {
+            Ok(mfa_token) => (StatusCode::OK, Json(json!({
+                "mfa_required": true,
+                "mfa_token": mfa_token,
+                "expires_in_seconds": MFA_LOGIN_TTL_MINUTES * 60,
+            }))).into_response(),
+            Err(e) => {
+                eprintln!("Auth Token Error: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "Database Error").into_response()
+            }
+        };
     }
 
-    // Gunakan user.email sebagai value cookie
-    let mut cookie = Cookie::new("session", user.email.clone()); 
-    cookie.set_path("/");
-    cookie.set_http_only(true);
-    cookie.set_same_site(tower_cookies::cookie::SameSite::Lax);
-    cookies.add(cookie);
+    if let Err(resp) = start_session(&state, &cookies, &user_id, ip, user_agent(&headers)).await {
+        return resp;
+    }
 
     (StatusCode::OK, "Login successful").into_response()
 }
 
-pub async fn logout(cookies: Cookies) -> impl IntoResponse {
-    let mut cookie = Cookie::new("session", "");
-    cookie.set_path("/");
-    cookies.remove(cookie);
-    (StatusCode::OK, "Logged out").into_response()
+// --- POST /auth/login/2fa: langkah kedua login dengan kode TOTP atau recovery code ---
+// mfa_token hanya berlaku sekali; kode salah berarti harus login ulang dari awal
+pub async fn login_mfa(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    cookies: Cookies,
+    Json(payload): Json<MfaLoginRequest>,
+) -> impl IntoResponse {
+    // Throttle per IP dicek sebelum mfa_token dipakai, agar IP yang terkunci tidak menghabiskan token
+    let ip = client_ip(&headers, addr);
+    if let Err(resp) = login_guard::ensure_not_throttled(&state, &[login_guard::ip_key(&ip)]).await {
+        return resp;
+    }
+
+    let token = match state.auth_token_repo.consume(&hash_token(&payload.mfa_token), PURPOSE_MFA_LOGIN).await {
+        Ok(Some(t)) => t,
+        Ok(None) => return (StatusCode::UNAUTHORIZED, "Invalid or expired 2FA login").into_response(),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Database Error").into_response(),
+    };
+
+    let Some(user) = state.user_repo.find_by_id(&token.user_id).await else {
+        return (StatusCode::UNAUTHORIZED, "User not found").into_response();
+    };
+    if let Err(resp) = login_guard::ensure_not_throttled(&state, &[login_guard::account_key(&user.email)]).await {
+        return resp;
+    }
+
+    match verify_second_factor(&state, &token.user_id, &user, &payload.code).await {
+        Ok(true) => login_guard::record_success(&state, &user.email).await,
+        Ok(false) => {
+            login_guard::record_failure(&state, &user.email, &ip).await;
+            return (StatusCode::UNAUTHORIZED, "Invalid 2FA code").into_response();
+        }
+        Err(e) => {
+            eprintln!("2FA Error: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Database Error").into_response();
+        }
+    }
+
+    if let Err(resp) = start_session(&state, &cookies, &token.user_id, ip, user_agent(&headers)).await {
+        return resp;
+    }
+
+    (StatusCode::OK, "Login successful").into_response()
 }
 
-pub async fn me(
+// Tukar refresh token dengan access token baru (refresh token ikut dirotasi)
+pub async fn refresh(
     State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     cookies: Cookies,
 ) -> impl IntoResponse {
-    if let Some(cookie) = cookies.get("session") {
-        let email = cookie.value();
-        if let Some(user) = state.user_repo.find_by_email(email).await {
-            // PERBAIKAN: Konversi ObjectId ke String
-            let user_id_str = user.id.map(|oid| oid.to_string()).unwrap_or_else(|| "404".to_string());
-
-            return (StatusCode::OK, Json(json!({
-                "id": user_id_str, // Sekarang ini adalah String murni
-                "name": user.name, 
-                "email": user.email,
-                "plan": user.plan,
-                "avatar": "https://github.com/shadcn.png"
-            }))).into_response();
+    let Some(refresh_cookie) = cookies.get(REFRESH_COOKIE) else {
+        return (StatusCode::UNAUTHORIZED, "No refresh token").into_response();
+    };
+    let old_hash = hash_token(refresh_cookie.value());
+
+    let session = match state.session_repo.find_active_by_refresh_hash(&old_hash).await {
+        Ok(Some(s)) => s,
+        Ok(None) => {
+            clear_auth_cookies(&cookies);
+            return (StatusCode::UNAUTHORIZED, "Invalid or revoked refresh token").into_response();
         }
+        Err(e) => {
+            eprintln!("Session Error: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Database Error").into_response();
+        }
+    };
+
+    let session_id = session.id.map(|oid| oid.to_hex()).unwrap_or_default();
+    if state.user_repo.find_by_id(&session.user_id).await.is_none() {
+        let _ = state.session_repo.revoke(&session_id, &session.user_id).await;
+        clear_auth_cookies(&cookies);
+        return (StatusCode::UNAUTHORIZED, "User not found").into_response();
     }
-    
-    (StatusCode::UNAUTHORIZED, "Not logged in").into_response()
-}
\ No newline at end of file
+
+    let new_refresh_token = generate_opaque_token();
+    match state.session_repo
+        .rotate_refresh_token(&session_id, &old_hash, &hash_token(&new_refresh_token), &client_ip(&headers, addr), &user_agent(&headers))
+        .await
+    {
+        Ok(true) => {}
+        // Token yang sama sudah dirotasi request lain: anggap bocor, sesi di-revoke
+        Ok(false) => {
+            if let Err(e) = state.session_repo.revoke(&session_id, &session.user_id).await {
+                eprintln!("Session Error: {}", e);
+            }
+            clear_auth_cookies(&cookies);
+            return (StatusCode::UNAUTHORIZED, "Refresh token reuse detected").into_response();
+        }
+        Err(e) => {
+            eprintln!("Session Error: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Database Error").into_response();
+        }
+    }
+
+    let access_token = match create_token(&session.user_id, &session_id, &state.jwt_secret) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("JWT Error: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Token Error").into_response();
+        }
+    };
+
+    set_auth_cookies(&cookies, access_token, new_refresh_token);
+    (StatusCode::OK, "Token refreshed").into_response()
+}
+
+// Logout me-revoke sesi di server, bukan hanya menghapus cookie di client
+pub async fn logout(
+    State(state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> impl IntoResponse {
+    if let Some(claims) = cookies.get(SESSION_COOKIE).and_then(|c| verify_token(c.value(), &state.jwt_secret).ok()) {
+        if let Err(e) = state.session_repo.revoke(&claims.sid, &claims.sub).await {
+            eprintln!("Session Error: {}", e);
+        }
+    } else if let Some(refresh_cookie) = cookies.get(REFRESH_COOKIE) {
+        if let Ok(Some(session)) = state.session_repo.find_active_by_refresh_hash(&hash_token(refresh_cookie.value())).await {
+            let session_id = session.id.map(|oid| oid.to_hex()).unwrap_or_default();
+            if let Err(e) = state.session_repo.revoke(&session_id, &session.user_id).await {
+                eprintln!("Session Error: {}", e);
+            }
+        }
+    }
+
+    clear_auth_cookies(&cookies);
+    (StatusCode::OK, "Logged out").into_response()
+}
+
+// --- POST /auth/verify-email ---
+pub async fn verify_email(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<TokenRequest>,
+) -> impl IntoResponse {
+    let token = match state.auth_token_repo.consume(&hash_token(&payload.token), PURPOSE_VERIFY_EMAIL).await {
+        Ok(Some(t)) => t,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Invalid or expired token").into_response(),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Database Error").into_response(),
+    };
+
+    match state.user_repo.set_email_verified(&token.user_id).await {
+        Ok(_) => (StatusCode::OK, "Email verified").into_response(),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Database Error").into_response(),
+    }
+}
+
+// --- POST /auth/forgot-password ---
+// Respon selalu sama agar tidak membocorkan email mana yang terdaftar
+pub async fn forgot_password(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> impl IntoResponse {
+    if let Some(user) = state.user_repo.find_by_email(&payload.email).await {
+        let user_id = user.id.map(|oid| oid.to_hex()).unwrap_or_default();
+
+        match issue_auth_token(&state, &user_id, PURPOSE_RESET_PASSWORD, Duration::minutes(RESET_PASSWORD_TTL_MINUTES)).await {
+            Ok(token) => {
+                let link = format!("{}/reset-password?token={}", state.app_base_url, token);
+                let body = format!("Halo,\n\nKlik link berikut untuk reset password akun Kepin Anda:\n{}\n\nLink berlaku {} menit. Abaikan email ini jika Anda tidak memintanya.", link, RESET_PASSWORD_TTL_MINUTES);
+                if let Err(e) = state.mailer.send(&user.email, "Reset Password Kepin", &body).await {
+                    eprintln!("Mailer Error: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Auth Token Error: {}", e),
+        }
+    }
+
+    (StatusCode::OK, "If the email is registered, a reset link has been sent").into_response()
+}
+
+// --- POST /auth/reset-password ---
+pub async fn reset_password(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> impl IntoResponse {
+    if payload.new_password.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Password must not be empty").into_response();
+    }
+
+    let token = match state.auth_token_repo.consume(&hash_token(&payload.token), PURPOSE_RESET_PASSWORD).await {
+        Ok(Some(t)) => t,
+        Ok(None) => return (StatusCode::BAD_REQUEST, "Invalid or expired token").into_response(),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Database Error").into_response(),
+    };
+
+    let new_password = payload.new_password;
+    let hashed_password = tokio::task::spawn_blocking(move || {
+        hash(new_password, DEFAULT_COST).unwrap()
+    }).await.unwrap();
+
+    if state.user_repo.update_password(&token.user_id, &hashed_password).await.is_err() {
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Database Error").into_response();
+    }
+
+    // Password berubah: semua sesi lama di-revoke
+    if let Err(e) = state.session_repo.revoke_all(&token.user_id).await {
+        eprintln!("Session Error: {}", e);
+    }
+    // Link reset berhasil membuktikan kepemilikan email
+    if let Err(e) = state.user_repo.set_email_verified(&token.user_id).await {
+        eprintln!("Database Error: {}", e);
+    }
+
+    (StatusCode::OK, "Password has been reset").into_response()
+}
+
+pub async fn me(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+) -> impl IntoResponse {
+    let plan = current.plan();
+    let quota = plans::quota_summary(&state, &current.id, plan).await;
+    let user = current.user;
+
+    (StatusCode::OK, Json(json!({
+        "id": current.id,
+        "name": user.name, 
+        "email": user.email,
+        "plan": plan.as_str(),
+        "role": user.role,
+        "two_factor_enabled": user.totp_enabled,
+        "quota": quota,
+        "avatar": user.avatar_url
+    }))).into_response()
+}
@@ -0,0 +1,161 @@
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use std::sync::Arc;
+use tokio_util::io::ReaderStream;
+use crate::core::current_user::CurrentUser;
+use crate::db::AppState;
+use crate::models::data_export::{DataExport, EXPORT_PENDING, EXPORT_READY};
+use crate::services::data_export::{run_export, EXPORT_PENDING_TIMEOUT_MINUTES, EXPORT_TTL_DAYS};
+
+fn db_error(e: mongodb::error::Error) -> Response {
+    eprintln!("Database Error: {}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "Database Error" }))).into_response()
+}
+
+fn export_json(export: &DataExport) -> serde_json::Value {
+    let id = export.id.map(|oid| oid.to_hex()).unwrap_or_default();
+    let download_url = (export.status == EXPORT_READY && export.expires_at > Utc::now())
+        .then(|| format!("/api/v1/auth/export/{}/download", id));
+
+    json!({
+        "id": id,
+        "status": export.status,
+        "size_bytes": export.size_bytes,
+        "error": export.error,
+        "download_url": download_url,
+        "created_at": export.created_at,
+        "expires_at": export.expires_at,
+    })
+}
+
+// --- POST /auth/export: mulai export di background, cek status lewat GET /auth/export/:id ---
+pub async fn request_export(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+
+    let since = Utc::now() - Duration::minutes(EXPORT_PENDING_TIMEOUT_MINUTES);
+    match state.data_export_repo.has_pending(&current.id, since).await {
+        Ok(true) => return (StatusCode::CONFLICT, Json(json!({ "error": "An export is already in progress" }))).into_response(),
+        Ok(false) => {}
+        Err(e) => return db_error(e),
+    }
+
+    let now = Utc::now();
+    let export = DataExport {
+        id: None,
+        user_id: current.id.clone(),
+        status: EXPORT_PENDING.to_string(),
+        file_path: None,
+        size_bytes: None,
+        error: None,
+        created_at: now,
+        completed_at: None,
+        expires_at: now + Duration::days(EXPORT_TTL_DAYS),
+    };
+
+    let export_id = match state.data_export_repo.create(export).await {
+        Ok(id) => id,
+        Err(e) => return db_error(e),
+    };
+
+    tokio::spawn(run_export(state.clone(), export_id.clone(), current.id.clone()));
+
+    (StatusCode::ACCEPTED, Json(json!({
+        "status": "success",
+        "id": export_id,
+        "export_status": EXPORT_PENDING,
+    }))).into_response()
+}
+
+// --- GET /auth/export ---
+pub async fn list_exports(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+
+    match state.data_export_repo.list_by_user(&current.id).await {
+        Ok(exports) => {
+            let data: Vec<_> = exports.iter().map(export_json).collect();
+            (StatusCode::OK, Json(json!({ "status": "success", "data": data }))).into_response()
+        }
+        Err(e) => db_error(e),
+    }
+}
+
+// --- GET /auth/export/:id ---
+pub async fn get_export(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+
+    match state.data_export_repo.find_for_user(&id, &current.id).await {
+        Ok(Some(export)) => (StatusCode::OK, Json(json!({ "status": "success", "data": export_json(&export) }))).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, Json(json!({ "error": "Export not found" }))).into_response(),
+        Err(_) => (StatusCode::BAD_REQUEST, Json(json!({ "error": "Invalid ID format" }))).into_response(),
+    }
+}
+
+// --- GET /auth/export/:id/download: stream file ZIP ---
+pub async fn download_export(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+
+    let export = match state.data_export_repo.find_for_user(&id, &current.id).await {
+        Ok(Some(export)) => export,
+        Ok(None) => return (StatusCode::NOT_FOUND, Json(json!({ "error": "Export not found" }))).into_response(),
+        Err(_) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Invalid ID format" }))).into_response(),
+    };
+
+    if export.status != EXPORT_READY {
+        return (StatusCode::CONFLICT, Json(json!({ "error": "Export is not ready", "export_status": export.status }))).into_response();
+    }
+    if export.expires_at <= Utc::now() {
+        return (StatusCode::GONE, Json(json!({ "error": "Export link has expired" }))).into_response();
+    }
+
+    let Some(file_path) = export.file_path else {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "Export file not found" }))).into_response();
+    };
+    let file = match tokio::fs::File::open(&file_path).await {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("Export Error: {}", e);
+            return (StatusCode::NOT_FOUND, Json(json!({ "error": "Export file not found" }))).into_response();
+        }
+    };
+
+    let file_name = format!("kepin-export-{}.zip", export.created_at.format("%Y%m%d"));
+    let mut response = Body::from_stream(ReaderStream::new(file)).into_response();
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_TYPE, header::HeaderValue::from_static("application/zip"));
+    if let Ok(value) = header::HeaderValue::from_str(&format!("attachment; filename=\"{}\"", file_name)) {
+        headers.insert(header::CONTENT_DISPOSITION, value);
+    }
+    if let Some(size) = export.size_bytes {
+        headers.insert(header::CONTENT_LENGTH, header::HeaderValue::from(size));
+    }
+    response
+}
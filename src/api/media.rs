@@ -0,0 +1,124 @@
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header::{CONTENT_LENGTH, CONTENT_TYPE}, HeaderValue, Method, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use crate::core::current_user::CurrentUser;
+use crate::core::media_path::{resolve_relative, PUBLIC_PREFIX, QUARANTINE_FOLDER};
+use crate::db::AppState;
+use crate::models::api_key::SCOPE_READ;
+
+// Owner file dari path /public (tanpa prefix). resolve_relative menolak bentuk tidak kanonik
+// ("uid/./quarantine/x", "uid//quarantine/x"), jadi segmen mentah di sini sama dengan key storage.
+// Err(400) = path tidak valid, Err(404) = file karantina (tidak disajikan, termasuk ke pemiliknya).
+fn media_owner(relative: &str) -> Result<&str, StatusCode> {
+    if resolve_relative(relative).is_err() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let mut segments = relative.split('/');
+    let owner = segments.next().unwrap_or_default();
+    if segments.next() == Some(QUARANTINE_FOLDER) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(owner)
+}
+
+// Guard untuk /public: file hanya bisa diakses pemiliknya,
+// atau member workspace tempat file tersebut di-upload.
+// Path di sini sudah tanpa prefix "/public", contoh: "/{user_id}/documents/file.xlsx"
+pub async fn guard_public_media(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    req: Request,
+    next: Next,
+) -> Response {
+    if let Err(resp) = current.require_scope(SCOPE_READ) {
+        return resp;
+    }
+
+    let relative = req.uri().path().trim_start_matches('/');
+
+    let owner = match media_owner(relative) {
+        Ok(owner) => owner,
+        Err(StatusCode::BAD_REQUEST) => return (StatusCode::BAD_REQUEST, "Invalid media path").into_response(),
+        Err(status) => return (status, "Not found").into_response(),
+    };
+    if owner == current.id {
+        return next.run(req).await;
+    }
+
+    // File milik user lain: boleh jika ada di workspace yang sama
+    let public_url = format!("{}{}", PUBLIC_PREFIX, relative);
+    let workspace_ids = state.upload_repo.find_workspace_ids_by_file_path(&public_url).await
+        .unwrap_or_default();
+
+    for ws in workspace_ids {
+        if let Ok(Some(_)) = state.workspace_repo.find_member(&ws, &current.id).await {
+            return next.run(req).await;
+        }
+    }
+
+    // 404 agar keberadaan file milik user lain tidak bocor
+    (StatusCode::NOT_FOUND, "Not found").into_response()
+}
+
+// Sajikan file dari storage (disk lokal atau S3) per chunk. Dipasang di belakang guard_public_media,
+// jadi path di sini sudah divalidasi. Path = key storage, contoh: "{user_id}/pdf/{sha256}.pdf"
+pub async fn serve_public_media(
+    State(state): State<Arc<AppState>>,
+    method: Method,
+    uri: Uri,
+) -> Response {
+    if method != Method::GET && method != Method::HEAD {
+        return StatusCode::METHOD_NOT_ALLOWED.into_response();
+    }
+
+    let key = uri.path().trim_start_matches('/');
+    let object = match state.storage.stream(key).await {
+        Ok(Some(object)) => object,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Not found").into_response(),
+        Err(e) => {
+            eprintln!("Storage Error: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Storage Error").into_response();
+        }
+    };
+
+    let mut response = Body::from_stream(object.body).into_response();
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(&object.content_type) {
+        headers.insert(CONTENT_TYPE, value);
+    }
+    if let Some(size) = object.size {
+        headers.insert(CONTENT_LENGTH, HeaderValue::from(size));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owner_from_canonical_path() {
+        assert_eq!(media_owner("user1/pdf/abc.pdf"), Ok("user1"));
+        assert_eq!(media_owner("user1/avatars/a.png"), Ok("user1"));
+    }
+
+    #[test]
+    fn quarantine_is_never_served() {
+        assert_eq!(media_owner("user1/quarantine/x.pdf"), Err(StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn non_canonical_quarantine_paths_are_rejected() {
+        assert_eq!(media_owner("user1/./quarantine/x.pdf"), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(media_owner("user1//quarantine/x.pdf"), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(media_owner("./user1/quarantine/x.pdf"), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(media_owner("user1/quarantine/x.pdf/"), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(media_owner("user1/pdf/../quarantine/x.pdf"), Err(StatusCode::BAD_REQUEST));
+    }
+}
@@ -1,6 +1,15 @@
 // src/api/mod.rs
 pub mod auth;
 pub mod uploads;
+pub mod media;
+pub mod sessions;
+pub mod api_keys;
+pub mod workspaces;
+pub mod admin;
+pub mod two_factor;
+pub mod oidc;
+pub mod profile;
+pub mod data_export;
 mod smart; // Private mod
 
 // Re-export 'analyze' agar terlihat seolah-olah ada di bawah 'api'
@@ -0,0 +1,213 @@
+use axum::{
+    extract::{ConnectInfo, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    Json,
+};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use std::{net::SocketAddr, sync::Arc};
+use tower_cookies::{Cookie, Cookies};
+use crate::api::auth::{client_ip, start_session, user_agent};
+use crate::core::auth_utils::{generate_opaque_token, hash_token};
+use crate::core::rbac::ROLE_ANALYST;
+use crate::db::AppState;
+use crate::models::external_identity::{ExternalIdentity, OidcCallbackQuery, OidcLoginState};
+use crate::models::user::User;
+use crate::services::oidc::{OidcClient, OidcUserInfo};
+
+// Batas waktu user menyelesaikan login di IdP
+const OIDC_STATE_TTL_MINUTES: i64 = 10;
+
+// Hash state login disimpan juga di cookie browser yang memulai login, agar callback
+// tidak bisa diselesaikan di browser lain (login CSRF)
+const OIDC_STATE_COOKIE: &str = "oidc_state";
+const OIDC_STATE_COOKIE_PATH: &str = "/api/v1/auth/oidc";
+
+fn set_state_cookie(cookies: &Cookies, state_hash: String) {
+    let mut cookie = Cookie::new(OIDC_STATE_COOKIE, state_hash);
+    cookie.set_path(OIDC_STATE_COOKIE_PATH);
+    cookie.set_http_only(true);
+    // Lax: cookie tetap terkirim saat IdP me-redirect (GET top-level) ke callback
+    cookie.set_same_site(tower_cookies::cookie::SameSite::Lax);
+    cookie.set_max_age(tower_cookies::cookie::time::Duration::minutes(OIDC_STATE_TTL_MINUTES));
+    cookies.add(cookie);
+}
+
+fn clear_state_cookie(cookies: &Cookies) {
+    let mut cookie = Cookie::new(OIDC_STATE_COOKIE, "");
+    cookie.set_path(OIDC_STATE_COOKIE_PATH);
+    cookies.remove(cookie);
+}
+
+fn not_configured() -> Response {
+    (StatusCode::NOT_FOUND, Json(json!({ "error": "OIDC login is not configured" }))).into_response()
+}
+
+// Callback dibuka oleh browser, jadi error dikembalikan sebagai redirect ke halaman login frontend
+fn redirect_error(state: &AppState, code: &str) -> Response {
+    Redirect::to(&format!("{}/login?error={}", state.app_base_url, code)).into_response()
+}
+
+// --- GET /auth/oidc/login: redirect ke IdP (authorization code + PKCE) ---
+pub async fn oidc_login(State(state): State<Arc<AppState>>, cookies: Cookies) -> impl IntoResponse {
+    let Some(oidc) = state.oidc.as_ref() else {
+        return not_configured();
+    };
+
+    let login_state = generate_opaque_token();
+    let code_verifier = generate_opaque_token();
+    let now = Utc::now();
+    let state_hash = hash_token(&login_state);
+
+    if let Err(e) = state.external_identity_repo.create_login_state(OidcLoginState {
+        id: None,
+        state_hash: state_hash.clone(),
+        code_verifier: code_verifier.clone(),
+        created_at: now,
+        expires_at: now + Duration::minutes(OIDC_STATE_TTL_MINUTES),
+    }).await {
+        eprintln!("Database Error: {}", e);
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "Database Error" }))).into_response();
+    }
+
+    match oidc.authorization_url(&login_state, &code_verifier).await {
+        Ok(url) => {
+            set_state_cookie(&cookies, state_hash);
+            Redirect::to(&url).into_response()
+        }
+        Err(e) => {
+            eprintln!("OIDC Discovery Error: {}", e);
+            (StatusCode::BAD_GATEWAY, Json(json!({ "error": "Identity provider unavailable" }))).into_response()
+        }
+    }
+}
+
+// Cari User untuk identitas IdP:
+// 1. identitas sudah ditautkan, 2. email terverifikasi cocok dengan akun lokal (ditautkan),
+// 3. auto-provisioning user baru pada login pertama
+async fn resolve_user(state: &AppState, oidc: &OidcClient, info: &OidcUserInfo) -> Result<String, &'static str> {
+    let provider = oidc.config.provider.as_str();
+
+    match state.external_identity_repo.find(provider, &info.sub).await {
+        Ok(Some(identity)) => {
+            return match state.user_repo.find_by_id(&identity.user_id).await {
+                Some(_) => Ok(identity.user_id),
+                None => Err("account_not_found"),
+            };
+        }
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("Database Error: {}", e);
+            return Err("server_error");
+        }
+    }
+
+    // Tanpa email terverifikasi, identitas tidak boleh ditautkan/dibuat berdasarkan email
+    let Some(email) = info.email.clone().filter(|_| info.email_verified) else {
+        return Err("email_not_verified");
+    };
+
+    let user_id = match state.user_repo.find_by_email(&email).await {
+        Some(user) => user.id.map(|oid| oid.to_hex()).ok_or("server_error")?,
+        None if oidc.config.auto_provision => {
+            let new_user = User {
+                id: None,
+                email: email.clone(),
+                name: info.name.clone().unwrap_or_default(),
+                // Tanpa password lokal: login hanya lewat IdP (bcrypt verify selalu gagal)
+                password: String::new(),
+                plan: "basic".to_string(),
+                email_verified: true,
+                role: ROLE_ANALYST.to_string(),
+                totp_secret: None,
+                totp_enabled: false,
+                totp_last_step: None,
+                recovery_codes: Vec::new(),
+                avatar_url: None,
+            };
+            state.user_repo.create_user(new_user).await.map_err(|e| {
+                eprintln!("Database Error: {}", e);
+                "server_error"
+            })?
+        }
+        None => return Err("account_not_found"),
+    };
+
+    state.external_identity_repo.create(ExternalIdentity {
+        id: None,
+        provider: provider.to_string(),
+        subject: info.sub.clone(),
+        user_id: user_id.clone(),
+        email: Some(email),
+        created_at: Utc::now(),
+    }).await.map_err(|e| {
+        eprintln!("Database Error: {}", e);
+        "server_error"
+    })?;
+
+    Ok(user_id)
+}
+
+// --- GET /auth/oidc/callback?code=...&state=... ---
+// 2FA lokal tidak diminta di sini: MFA menjadi tanggung jawab IdP
+pub async fn oidc_callback(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    cookies: Cookies,
+    Query(query): Query<OidcCallbackQuery>,
+) -> impl IntoResponse {
+    let Some(oidc) = state.oidc.as_ref() else {
+        return not_configured();
+    };
+
+    // Cookie state hanya berlaku untuk satu callback
+    let cookie_state = cookies.get(OIDC_STATE_COOKIE).map(|c| c.value().to_string());
+    clear_state_cookie(&cookies);
+
+    if let Some(error) = query.error {
+        eprintln!("OIDC Provider Error: {}", error);
+        return redirect_error(&state, "provider_error");
+    }
+    let (Some(code), Some(login_state)) = (query.code, query.state) else {
+        return redirect_error(&state, "invalid_request");
+    };
+
+    let state_hash = hash_token(&login_state);
+    if cookie_state.as_deref() != Some(state_hash.as_str()) {
+        return redirect_error(&state, "invalid_state");
+    }
+
+    let pending = match state.external_identity_repo.consume_login_state(&state_hash).await {
+        Ok(Some(p)) => p,
+        Ok(None) => return redirect_error(&state, "invalid_state"),
+        Err(e) => {
+            eprintln!("Database Error: {}", e);
+            return redirect_error(&state, "server_error");
+        }
+    };
+
+    let info = match oidc.exchange_code(&code, &pending.code_verifier).await {
+        Ok(token) => oidc.userinfo(&token.access_token).await,
+        Err(e) => Err(e),
+    };
+    let info = match info {
+        Ok(info) => info,
+        Err(e) => {
+            eprintln!("OIDC Token Error: {}", e);
+            return redirect_error(&state, "token_exchange_failed");
+        }
+    };
+
+    let user_id = match resolve_user(&state, oidc, &info).await {
+        Ok(id) => id,
+        Err(code) => return redirect_error(&state, code),
+    };
+
+    if let Err(resp) = start_session(&state, &cookies, &user_id, client_ip(&headers, addr), user_agent(&headers)).await {
+        return resp;
+    }
+
+    Redirect::to(&state.app_base_url).into_response()
+}
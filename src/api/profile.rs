@@ -0,0 +1,322 @@
+use axum::{
+    extract::{Multipart, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use bcrypt::{hash, verify, DEFAULT_COST};
+use mongodb::bson::doc;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::fs::remove_dir_all;
+use tower_cookies::Cookies;
+use crate::api::auth::{clear_auth_cookies, send_verification_email};
+use crate::api::uploads::store_media_file;
+use crate::core::current_user::CurrentUser;
+use crate::core::file_sniff;
+use crate::core::media_path;
+use crate::db::AppState;
+use crate::models::user::{ChangePasswordRequest, DeleteAccountRequest, UpdateProfileRequest};
+use crate::models::workspace::Scope;
+use crate::repository::is_duplicate_key;
+use crate::services::data_export::EXPORT_ROOT;
+use crate::services::cascade;
+
+const AVATAR_FOLDER: &str = "avatar";
+const AVATAR_MAX_BYTES: usize = 2 * 1024 * 1024;
+
+fn db_error(e: mongodb::error::Error) -> Response {
+    eprintln!("Database Error: {}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "Database Error" }))).into_response()
+}
+
+async fn delete_avatar(state: &AppState, avatar_url: &str) {
+    if let Ok(key) = media_path::public_url_to_key(avatar_url) {
+        if let Err(e) = state.storage.delete(&key).await {
+            eprintln!("Warning: Avatar lama gagal dihapus: {}", e);
+        }
+    }
+}
+
+async fn password_matches(password: String, password_hash: String) -> bool {
+    tokio::task::spawn_blocking(move || verify(password, &password_hash).unwrap_or(false))
+        .await
+        .unwrap_or(false)
+}
+
+// --- PATCH /auth/me: ubah nama dan/atau email ---
+// Email baru harus diverifikasi ulang sebelum bisa dipakai login dengan password
+pub async fn update_me(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Json(payload): Json<UpdateProfileRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+
+    let mut fields = mongodb::bson::Document::new();
+
+    if let Some(name) = payload.name {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Name must not be empty" }))).into_response();
+        }
+        fields.insert("name", name);
+    }
+
+    let mut new_email = None;
+    if let Some(email) = payload.email {
+        let email = email.trim().to_string();
+        if !email.contains('@') {
+            return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Invalid email" }))).into_response();
+        }
+        if email != current.user.email {
+            // Ganti email + forgot-password = ambil alih akun, jadi sesi saja tidak cukup
+            if !current.user.password.is_empty() {
+                let Some(password) = payload.current_password else {
+                    return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Current password is required to change email" }))).into_response();
+                };
+                if !password_matches(password, current.user.password.clone()).await {
+                    return (StatusCode::UNAUTHORIZED, Json(json!({ "error": "Invalid password" }))).into_response();
+                }
+            }
+            if state.user_repo.find_by_email(&email).await.is_some() {
+                return (StatusCode::CONFLICT, Json(json!({ "error": "Email already exists" }))).into_response();
+            }
+            fields.insert("email", email.clone());
+            fields.insert("email_verified", false);
+            new_email = Some(email);
+        }
+    }
+
+    if fields.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Nothing to update" }))).into_response();
+    }
+
+    match state.user_repo.update_fields(&current.id, fields).await {
+        Ok(_) => {}
+        Err(e) if is_duplicate_key(&e) => {
+            return (StatusCode::CONFLICT, Json(json!({ "error": "Email already exists" }))).into_response();
+        }
+        Err(e) => return db_error(e),
+    }
+    if let Some(email) = &new_email {
+        send_verification_email(&state, &current.id, email).await;
+    }
+
+    let user = state.user_repo.find_by_id(&current.id).await.unwrap_or(current.user);
+    (StatusCode::OK, Json(json!({
+        "status": "success",
+        "name": user.name,
+        "email": user.email,
+        "email_verified": user.email_verified,
+    }))).into_response()
+}
+
+// --- POST /auth/change-password ---
+pub async fn change_password(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+    if payload.new_password.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Password must not be empty" }))).into_response();
+    }
+    if !password_matches(payload.old_password, current.user.password.clone()).await {
+        return (StatusCode::UNAUTHORIZED, Json(json!({ "error": "Old password is incorrect" }))).into_response();
+    }
+
+    let new_password = payload.new_password;
+    let hashed_password = tokio::task::spawn_blocking(move || {
+        hash(new_password, DEFAULT_COST).unwrap()
+    }).await.unwrap();
+
+    if let Err(e) = state.user_repo.update_password(&current.id, &hashed_password).await {
+        return db_error(e);
+    }
+
+    // Device lain harus login ulang, sesi yang sedang dipakai tetap aktif
+    if let Some(session_id) = current.session_id() {
+        if let Err(e) = state.session_repo.revoke_all_except(&current.id, session_id).await {
+            eprintln!("Session Error: {}", e);
+        }
+    }
+
+    (StatusCode::OK, Json(json!({ "status": "success", "message": "Password changed" }))).into_response()
+}
+
+// --- POST /auth/me/avatar (multipart field "file", hanya gambar) ---
+pub async fn upload_avatar(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+
+    let mut file_data = Vec::new();
+    let mut file_name = String::new();
+
+    // Error multipart (body terpotong, melewati batas ukuran request, dll.) dikembalikan apa adanya
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => return (e.status(), e.body_text()).into_response(),
+        };
+        if field.name() == Some("file") {
+            file_name = field.file_name().unwrap_or("avatar").to_string();
+            file_data = match field.bytes().await {
+                Ok(bytes) => bytes.to_vec(),
+                Err(e) => return (e.status(), e.body_text()).into_response(),
+            };
+        }
+    }
+
+    if file_data.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "No file provided" }))).into_response();
+    }
+    if file_data.len() > AVATAR_MAX_BYTES {
+        return (StatusCode::PAYLOAD_TOO_LARGE, Json(json!({ "error": "Avatar too large (2 MB max)" }))).into_response();
+    }
+
+    // Jenis gambar ditentukan dari isi file, bukan MIME kiriman client
+    let format = match file_sniff::sniff(&file_data[..file_data.len().min(file_sniff::SNIFF_LEN)], &file_name) {
+        Some(format) if format.file_type() == file_sniff::TYPE_IMAGE => format,
+        _ => return (StatusCode::UNSUPPORTED_MEDIA_TYPE, Json(json!({ "error": "Avatar must be a PNG, JPEG, GIF or WebP image" }))).into_response(),
+    };
+
+    let public_url = match store_media_file(&state, &current.id, AVATAR_FOLDER, format, &file_data).await {
+        Ok(saved) => saved.public_url,
+        Err(resp) => return resp,
+    };
+
+    if let Err(e) = state.user_repo.update_fields(&current.id, doc! { "avatar_url": &public_url }).await {
+        return db_error(e);
+    }
+
+    // Avatar lama tidak dipakai lagi
+    if let Some(old_url) = &current.user.avatar_url {
+        delete_avatar(&state, old_url).await;
+    }
+
+    (StatusCode::OK, Json(json!({ "status": "success", "avatar": public_url }))).into_response()
+}
+
+// Hapus semua upload di satu scope beserta financial report dan file fisiknya
+async fn delete_scope_data(state: &AppState, scope: &Scope) -> mongodb::error::Result<usize> {
+    let uploads = state.upload_repo.find_in_scope(scope).await?;
+    let total = uploads.len();
+
+    for upload in uploads {
+        cascade::delete_upload(state, &upload, false).await?;
+    }
+    Ok(total)
+}
+
+// --- DELETE /auth/me: hapus akun ---
+// Data pribadi dan workspace milik user (yang tidak punya member lain) ikut dihapus.
+// Upload user di workspace orang lain tetap menjadi milik workspace tersebut.
+pub async fn delete_me(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    cookies: Cookies,
+    Json(payload): Json<DeleteAccountRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+
+    if !current.user.password.is_empty() {
+        let Some(password) = payload.password else {
+            return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Password is required" }))).into_response();
+        };
+        if !password_matches(password, current.user.password.clone()).await {
+            return (StatusCode::UNAUTHORIZED, Json(json!({ "error": "Invalid password" }))).into_response();
+        }
+    }
+
+    // Workspace milik user yang masih punya member lain harus dialihkan/dikosongkan dulu
+    let memberships = match state.workspace_repo.list_for_user(&current.id).await {
+        Ok(m) => m,
+        Err(e) => return db_error(e),
+    };
+    let mut owned_workspaces = Vec::new();
+    for (workspace, _) in memberships {
+        if workspace.owner_id != current.id {
+            continue;
+        }
+        let workspace_id = workspace.id.map(|oid| oid.to_hex()).unwrap_or_default();
+        match state.workspace_repo.list_members(&workspace_id).await {
+            Ok(members) if members.iter().any(|m| m.user_id != current.id) => {
+                return (StatusCode::CONFLICT, Json(json!({
+                    "error": "Remove the other members of your workspaces before deleting your account",
+                    "workspace_id": workspace_id,
+                    "workspace": workspace.name,
+                }))).into_response();
+            }
+            Ok(_) => owned_workspaces.push(workspace_id),
+            Err(e) => return db_error(e),
+        }
+    }
+
+    let mut scopes = vec![Scope::personal(&current.id)];
+    scopes.extend(owned_workspaces.iter().map(|ws| Scope {
+        user_id: current.id.clone(),
+        workspace_id: Some(ws.clone()),
+        role: current.scope.role.clone(),
+    }));
+
+    let mut deleted_uploads = 0;
+    for scope in &scopes {
+        match delete_scope_data(&state, scope).await {
+            Ok(count) => deleted_uploads += count,
+            Err(e) => return db_error(e),
+        }
+    }
+    for workspace_id in &owned_workspaces {
+        if let Err(e) = state.workspace_repo.delete(workspace_id).await {
+            return db_error(e);
+        }
+    }
+    if let Err(e) = state.workspace_repo.remove_all_memberships(&current.id).await {
+        return db_error(e);
+    }
+
+    if let Some(avatar_url) = &current.user.avatar_url {
+        delete_avatar(&state, avatar_url).await;
+    }
+
+    // File export data akun lama ikut dihapus
+    let _ = remove_dir_all(std::path::Path::new(EXPORT_ROOT).join(&current.id)).await;
+    if let Err(e) = state.data_export_repo.delete_by_user(&current.id).await {
+        eprintln!("Database Error: {}", e);
+    }
+
+    // Semua kredensial dicabut sebelum user dihapus
+    if let Err(e) = state.session_repo.revoke_all(&current.id).await {
+        eprintln!("Session Error: {}", e);
+    }
+    if let Err(e) = state.api_key_repo.revoke_all(&current.id).await {
+        eprintln!("API Key Error: {}", e);
+    }
+    if let Err(e) = state.external_identity_repo.delete_by_user(&current.id).await {
+        eprintln!("Database Error: {}", e);
+    }
+    if let Err(e) = state.user_repo.delete_user(&current.id).await {
+        return db_error(e);
+    }
+
+    clear_auth_cookies(&cookies);
+    (StatusCode::OK, Json(json!({
+        "status": "success",
+        "message": "Account deleted",
+        "deleted_uploads": deleted_uploads,
+        "deleted_workspaces": owned_workspaces.len(),
+    }))).into_response()
+}
@@ -0,0 +1,80 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde_json::json;
+use std::sync::Arc;
+use crate::core::current_user::CurrentUser;
+use crate::db::AppState;
+
+// --- GET /auth/sessions: daftar sesi aktif milik user ---
+pub async fn list_sessions(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+
+    match state.session_repo.list_active_by_user(&current.id).await {
+        Ok(sessions) => {
+            let data: Vec<_> = sessions.into_iter().map(|s| {
+                let id = s.id.map(|oid| oid.to_hex()).unwrap_or_default();
+                json!({
+                    "current": Some(id.as_str()) == current.session_id(),
+                    "id": id,
+                    "user_agent": s.user_agent,
+                    "ip": s.ip,
+                    "created_at": s.created_at,
+                    "last_used_at": s.last_used_at,
+                    "expires_at": s.expires_at,
+                })
+            }).collect();
+
+            (StatusCode::OK, Json(json!({ "status": "success", "data": data }))).into_response()
+        }
+        Err(e) => {
+            eprintln!("Database Error: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "Failed fetch" }))).into_response()
+        }
+    }
+}
+
+// --- DELETE /auth/sessions/:id: revoke satu sesi ---
+pub async fn revoke_session(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+
+    match state.session_repo.revoke(&id, &current.id).await {
+        Ok(count) if count > 0 => {
+            (StatusCode::OK, Json(json!({ "status": "success", "id": id }))).into_response()
+        }
+        Ok(_) => (StatusCode::NOT_FOUND, Json(json!({ "error": "Session not found" }))).into_response(),
+        Err(_) => (StatusCode::BAD_REQUEST, Json(json!({ "error": "Invalid ID format" }))).into_response(),
+    }
+}
+
+// --- DELETE /auth/sessions: revoke semua sesi (logout dari semua device) ---
+pub async fn revoke_all_sessions(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+
+    match state.session_repo.revoke_all(&current.id).await {
+        Ok(count) => (StatusCode::OK, Json(json!({ "status": "success", "revoked": count }))).into_response(),
+        Err(e) => {
+            eprintln!("Database Error: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))).into_response()
+        }
+    }
+}
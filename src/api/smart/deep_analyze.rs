@@ -6,14 +6,19 @@ use futures::stream::StreamExt;
 use reqwest::{header::{AUTHORIZATION, CONTENT_TYPE}, Client};
 use serde::Deserialize; // Pastikan ini ada
 use serde_json::json;
-use std::{convert::Infallible, time::Duration, sync::{Arc, OnceLock}, path::Path};
-use tokio::{fs, task};
+use std::{convert::Infallible, time::Duration, sync::{Arc, OnceLock}};
+use tokio::task;
 use std::io::Cursor;
 use calamine::{Reader, Xlsx, Data};
 use chrono::Utc;
 use std::fmt::Write; 
 
+use crate::core::current_user::CurrentUser;
+use crate::core::media_path;
+use crate::core::plans;
+use crate::api::smart::reuse;
 use crate::db::AppState;
+use crate::models::api_key::{SCOPE_ANALYZE};
 use crate::models::financial::{FinancialData, FinancialRecord};
 use crate::services::extractor_client::financial_proto::analyze_response::Result as ProtoResult; 
 
@@ -22,9 +27,9 @@ static HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
 // --- STRUCT REQUEST (Pastikan ini ada di file ini) ---
 #[derive(Deserialize)]
 pub struct AnalyzeRequest {
-    pub file_path: String,
-    pub user_id: String,
-    pub id_userupload: String,
+    pub id_userupload: String, // path file diambil dari record upload milik user
+    #[serde(default)]
+    pub reuse_existing: bool, // pakai hasil analisa file identik yang sudah ada
 }
 
 // --- HELPER PARSING ---
@@ -68,23 +73,64 @@ fn deep_excel_bytes_to_csv_optimized(bytes: Vec<u8>, limit: usize) -> Result<Str
 // --- HANDLER UTAMA ---
 pub async fn deep_analyze_document_stream(
     State(state): State<Arc<AppState>>,
+    current: CurrentUser,
     Json(payload): Json<AnalyzeRequest>, 
 ) -> impl IntoResponse {
+    if let Err(resp) = current.require_scope(SCOPE_ANALYZE) {
+        return resp;
+    }
+    if let Err(resp) = current.require_write() {
+        return resp;
+    }
+
+    // Isi file identik sudah pernah dianalisa: kirim hasil yang ada tanpa memakai kuota
+    if payload.reuse_existing {
+        if let Some(resp) = reuse::reuse_existing_analysis(&state, &current, &payload.id_userupload).await {
+            return resp;
+        }
+    }
+
+    // Mode & kuota analisa sesuai plan user
+    if let Err(resp) = plans::ensure_analyze_allowed(&state, &current.id, current.plan(), plans::MODE_DEEP).await {
+        return resp;
+    }
 
-    let relative_path = payload.file_path.trim_start_matches("/public/");
-    let file_path = Path::new("media").join(relative_path);
+    // Hanya upload di scope user/workspace yang boleh dianalisa
+    let upload = match state.upload_repo.find_in_scope_by_id(&payload.id_userupload, &current.scope).await {
+        Ok(Some(u)) => u,
+        _ => return Sse::new(futures::stream::iter(vec![
+            Ok::<Event, Infallible>(Event::default().data("ERR_FILE: Upload not found"))
+        ])).into_response(),
+    };
+    // File yang ditandai scanner malware tidak pernah dikirim ke parser / AI
+    if upload.is_quarantined() {
+        return Sse::new(futures::stream::iter(vec![
+            Ok::<Event, Infallible>(Event::default().data("ERR_FILE: File quarantined by malware scan"))
+        ])).into_response();
+    }
+
+    // File dibaca lewat storage (lokal / S3) berdasarkan key dari URL publik upload
+    let file_key = match media_path::public_url_to_key(&upload.file_path) {
+        Ok(k) => k,
+        Err(e) => return Sse::new(futures::stream::iter(vec![
+            Ok::<Event, Infallible>(Event::default().data(format!("ERR_FILE: {}", e)))
+        ])).into_response(),
+    };
+    let file_path = std::path::Path::new(&file_key);
     let extension = file_path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
-    let user_id = payload.user_id.clone();
+    let user_id = current.id;
     let upload_id = payload.id_userupload.clone();
-    let file_path_str = payload.file_path.clone();
+    let file_path_str = upload.file_path.clone();
+    let workspace_id = upload.workspace_id.clone();
     let filename = file_path.file_name().unwrap().to_string_lossy().to_string();
 
-    let file_bytes = match fs::read(&file_path).await {
+    let file_bytes = match state.storage.get(&file_key).await {
         Ok(b) => b,
         Err(e) => return Sse::new(futures::stream::iter(vec![
             Ok::<Event, Infallible>(Event::default().data(format!("ERR_FILE: {}", e)))
         ])).into_response(),
     };
+    plans::record_analysis(&state, &user_id).await;
 
     let grpc_client = state.grpc_client.clone();
     let state_clone = state.clone();
@@ -234,6 +280,7 @@ OUTPUT SCHEMA (Strict JSON):
                     id: None,
                     user_id: user_id,
                     id_userupload: upload_id,
+                    workspace_id,
                     source_file: file_path_str,
                     data: financial_data,
                     created_at: Utc::now(),
@@ -3,20 +3,24 @@ use axum::{
     extract::{Json, State},
     response::{sse::{Event, KeepAlive, Sse}, IntoResponse},
 };
-use std::{convert::Infallible, time::Duration, sync::Arc, path::Path};
-use tokio::fs;
+use std::{convert::Infallible, time::Duration, sync::Arc};
+use crate::core::current_user::CurrentUser;
+use crate::core::media_path;
+use crate::core::plans;
+use crate::api::smart::reuse;
 use crate::db::AppState;
+use crate::models::api_key::{SCOPE_ANALYZE};
 use crate::models::financial::{FinancialData, FinancialRecord, FinancialItem};
 use chrono::Utc;
 use crate::services::extractor_client::financial_proto::analyze_response::Result as ProtoResult; 
 
 #[derive(serde::Deserialize)]
 pub struct AnalyzeRequestDTO {
-    pub file_path: String,
-    pub user_id: String,
-    pub id_userupload: String,
+    pub id_userupload: String, // path file diambil dari record upload milik user
     #[serde(default = "default_mode")] 
     pub mode: String, 
+    #[serde(default)]
+    pub reuse_existing: bool, // pakai hasil analisa file identik yang sudah ada
 }
 
 fn default_mode() -> String {
@@ -25,28 +29,68 @@ fn default_mode() -> String {
 
 pub async fn fast_analyze_document_stream(
     State(state): State<Arc<AppState>>,
+    current: CurrentUser,
     Json(payload): Json<AnalyzeRequestDTO>, 
 ) -> impl IntoResponse {
+    if let Err(resp) = current.require_scope(SCOPE_ANALYZE) {
+        return resp;
+    }
+    if let Err(resp) = current.require_write() {
+        return resp;
+    }
+
+    // Isi file identik sudah pernah dianalisa: kirim hasil yang ada tanpa memakai kuota
+    if payload.reuse_existing {
+        if let Some(resp) = reuse::reuse_existing_analysis(&state, &current, &payload.id_userupload).await {
+            return resp;
+        }
+    }
+
+    // Mode & kuota analisa sesuai plan user
+    if let Err(resp) = plans::ensure_analyze_allowed(&state, &current.id, current.plan(), plans::MODE_FAST).await {
+        return resp;
+    }
+
     // Audit ID unik untuk tracking satu sesi request
-    let audit_id = format!("{}-{}", payload.user_id, Utc::now().timestamp_micros());
+    let user_id = current.id;
+    let audit_id = format!("{}-{}", user_id, Utc::now().timestamp_micros());
     
     // LOG AUDIT: Request Masuk
     println!("[AUDIT][{}] === NEW REQUEST ===", audit_id);
-    println!("[AUDIT][{}] User: {}, File: {}, Mode: {}", audit_id, payload.user_id, payload.file_path, payload.mode);
+    println!("[AUDIT][{}] User: {}, Upload: {}, Mode: {}", audit_id, user_id, payload.id_userupload, payload.mode);
 
-    // 1. Validasi File
-    let relative_path = payload.file_path.trim_start_matches("/public/");
-    let file_path = Path::new("media").join(relative_path);
-    
-    if !file_path.exists() {
-        println!("[AUDIT][{}] ERROR: File not found at {:?}", audit_id, file_path);
+    // 1. Validasi File (hanya upload di scope user/workspace)
+    let upload = match state.upload_repo.find_in_scope_by_id(&payload.id_userupload, &current.scope).await {
+        Ok(Some(u)) => u,
+        _ => {
+            println!("[AUDIT][{}] ERROR: Upload {} not owned or not found", audit_id, payload.id_userupload);
+            return Sse::new(futures::stream::iter(vec![
+                Ok::<Event, Infallible>(Event::default().event("error").data("ERR_FILE: Upload not found"))
+            ])).into_response();
+        }
+    };
+    // File yang ditandai scanner malware tidak pernah dikirim ke parser / AI
+    if upload.is_quarantined() {
+        println!("[AUDIT][{}] ERROR: Upload {} quarantined ({})", audit_id, payload.id_userupload, upload.scan_signature.as_deref().unwrap_or("-"));
         return Sse::new(futures::stream::iter(vec![
-            Ok::<Event, Infallible>(Event::default().event("error").data("ERR_FILE: File not found"))
+            Ok::<Event, Infallible>(Event::default().event("error").data("ERR_FILE: File quarantined by malware scan"))
         ])).into_response();
     }
 
+    // File dibaca lewat storage (lokal / S3) berdasarkan key dari URL publik upload
+    let file_key = match media_path::public_url_to_key(&upload.file_path) {
+        Ok(k) => k,
+        Err(e) => {
+            println!("[AUDIT][{}] ERROR: Rejected media path {}: {}", audit_id, upload.file_path, e);
+            return Sse::new(futures::stream::iter(vec![
+                Ok::<Event, Infallible>(Event::default().event("error").data(format!("ERR_FILE: {}", e)))
+            ])).into_response();
+        }
+    };
+    let file_path = std::path::Path::new(&file_key);
+    
     // 2. Baca File
-    let file_bytes = match fs::read(&file_path).await {
+    let file_bytes = match state.storage.get(&file_key).await {
         Ok(b) => {
             println!("[AUDIT][{}] Success: File read ({} bytes)", audit_id, b.len());
             b
@@ -59,13 +103,15 @@ pub async fn fast_analyze_document_stream(
         }
     };
 
+    plans::record_analysis(&state, &user_id).await;
+
     let extension = file_path.extension().and_then(|e| e.to_str()).unwrap_or("").to_string();
     let grpc_client = state.grpc_client.clone();
     let filename = file_path.file_name().unwrap().to_string_lossy().to_string();
-    let user_id = payload.user_id.clone();
     let upload_id = payload.id_userupload.clone();
     let state_clone = state.clone();
-    let source_file_str = payload.file_path.clone();
+    let source_file_str = upload.file_path.clone();
+    let workspace_id = upload.workspace_id.clone();
     let analyze_mode = payload.mode.clone(); 
 
     // 3. Eksekusi Stream
@@ -102,6 +148,7 @@ pub async fn fast_analyze_document_stream(
                                 id: None,
                                 user_id: user_id.clone(),
                                 id_userupload: upload_id.clone(),
+                                workspace_id: workspace_id.clone(),
                                 source_file: source_file_str.clone(),
                                 data: FinancialData {
                                     nama_entitas: res.nama_entitas,
@@ -1,4 +1,5 @@
 // src/api/smart/mod.rs
 pub mod fast_analyze;
 pub mod deep_analyze;
-pub mod normal_analyze;
\ No newline at end of file
+pub mod normal_analyze;
+pub mod reuse;
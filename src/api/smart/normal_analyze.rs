@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Json, State, Query}, 
+    extract::{Json, State}, 
     http::StatusCode,
     response::{sse::{Event, KeepAlive, Sse}, IntoResponse},
 };
@@ -11,15 +11,19 @@ use std::{
     convert::Infallible,
     fmt::Write,
     io::Cursor,
-    path::Path,
     sync::{Arc, OnceLock},
     time::Duration,
 };
-use tokio::{fs, task};
+use tokio::task;
 use calamine::{Data, Reader, Xlsx};
 use chrono::Utc;
 
+use crate::core::current_user::CurrentUser;
+use crate::core::media_path;
+use crate::core::plans;
+use crate::api::smart::reuse;
 use crate::db::AppState;
+use crate::models::api_key::{SCOPE_ANALYZE, SCOPE_READ};
 use crate::models::financial::{FinancialData, FinancialRecord};
 
 static HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
@@ -27,15 +31,9 @@ static HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
 // --- DTO: Request Body untuk Analisa ---
 #[derive(Deserialize)]
 pub struct AnalyzeRequest {
-    pub file_path: String,
-    pub user_id: String,
-    pub id_userupload: String, // Wajib dikirim frontend
-}
-
-// --- DTO: Query Param untuk GET Data ---
-#[derive(Deserialize)]
-pub struct FinancialQuery {
-    pub user_id: String,
+    pub id_userupload: String, // Wajib dikirim frontend, path file diambil dari record upload
+    #[serde(default)]
+    pub reuse_existing: bool, // pakai hasil analisa file identik yang sudah ada
 }
 
 // --- Helper Structs Parsing AI ---
@@ -55,9 +53,13 @@ struct StreamDelta {
 // --- Handler 1: GET Financial Data ---
 pub async fn get_financial_data(
     State(state): State<Arc<AppState>>,
-    Query(query): Query<FinancialQuery>
+    current: CurrentUser,
 ) -> impl IntoResponse {
-    match state.financial_repo.find_by_user(&query.user_id).await {
+    if let Err(resp) = current.require_scope(SCOPE_READ) {
+        return resp;
+    }
+
+    match state.financial_repo.find_in_scope(&current.scope).await {
         Ok(records) => (StatusCode::OK, Json(records)).into_response(),
         Err(e) => {
             eprintln!("Database Error: {}", e);
@@ -70,10 +72,14 @@ pub async fn get_financial_data(
 // dashboard get financial stats
 pub async fn get_financial_stats(
     State(state): State<Arc<AppState>>,
-    Query(query): Query<FinancialQuery>
+    current: CurrentUser,
 ) -> impl IntoResponse {
+    if let Err(resp) = current.require_scope(SCOPE_READ) {
+        return resp;
+    }
+
     // Panggil fungsi yang ada di dalam repository melalui state
-    match state.upload_repo.get_uploads_stats(&query.user_id).await {
+    match state.upload_repo.get_uploads_stats(&current.scope).await {
         Ok(stats_data) => {
             (StatusCode::OK, Json(serde_json::json!({
                 "status": "success",
@@ -134,14 +140,53 @@ fn normal_excel_bytes_to_csv_optimized(bytes: Vec<u8>, limit: usize) -> Result<S
 // --- Handler 2: Analyze Stream (POST) ---
 pub async fn normal_analyze_document_stream(
     State(state): State<Arc<AppState>>,
+    current: CurrentUser,
     Json(payload): Json<AnalyzeRequest>,
 ) -> impl IntoResponse {
-    
-    let relative_path = payload.file_path.trim_start_matches("/public/");
-    let file_path = Path::new("media").join(relative_path);
+    if let Err(resp) = current.require_scope(SCOPE_ANALYZE) {
+        return resp;
+    }
+    if let Err(resp) = current.require_write() {
+        return resp;
+    }
+
+    // Isi file identik sudah pernah dianalisa: kirim hasil yang ada tanpa memakai kuota
+    if payload.reuse_existing {
+        if let Some(resp) = reuse::reuse_existing_analysis(&state, &current, &payload.id_userupload).await {
+            return resp;
+        }
+    }
+
+    // Mode & kuota analisa sesuai plan user
+    if let Err(resp) = plans::ensure_analyze_allowed(&state, &current.id, current.plan(), plans::MODE_NORMAL).await {
+        return resp;
+    }
+
+    // Hanya upload di scope user/workspace yang boleh dianalisa
+    let upload = match state.upload_repo.find_in_scope_by_id(&payload.id_userupload, &current.scope).await {
+        Ok(Some(u)) => u,
+        _ => return Sse::new(futures::stream::iter(vec![
+            Ok::<Event, Infallible>(Event::default().data("ERR_FILE: Upload not found"))
+        ])).into_response(),
+    };
+    // File yang ditandai scanner malware tidak pernah dikirim ke parser / AI
+    if upload.is_quarantined() {
+        return Sse::new(futures::stream::iter(vec![
+            Ok::<Event, Infallible>(Event::default().data("ERR_FILE: File quarantined by malware scan"))
+        ])).into_response();
+    }
+
+    // File dibaca lewat storage (lokal / S3) berdasarkan key dari URL publik upload
+    let file_key = match media_path::public_url_to_key(&upload.file_path) {
+        Ok(k) => k,
+        Err(e) => return Sse::new(futures::stream::iter(vec![
+            Ok::<Event, Infallible>(Event::default().data(format!("ERR_FILE: {}", e)))
+        ])).into_response(),
+    };
+    let file_path = std::path::Path::new(&file_key);
     let extension = file_path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
     
-    let file_bytes = match fs::read(&file_path).await {
+    let file_bytes = match state.storage.get(&file_key).await {
         Ok(b) => b,
         Err(e) => return Sse::new(futures::stream::iter(vec![
             Ok::<Event, Infallible>(Event::default().data(format!("ERR_FILE: {}", e)))
@@ -171,6 +216,7 @@ pub async fn normal_analyze_document_stream(
             Ok::<Event, Infallible>(Event::default().data(format!("ERR_PARSE: {}", e)))
         ])).into_response(),
     };
+    plans::record_analysis(&state, &current.id).await;
 
     let system_prompt = r#"You are a high-precision Financial Data Extraction Engine specialized in Indonesian financial statements (Laporan Keuangan). 
     Your goal is to parse MULTIPLE SHEETS and consolidate data into a single, strict JSON output.
@@ -229,8 +275,9 @@ pub async fn normal_analyze_document_stream(
         }));
 
     let state_clone = state.clone();
-    let file_path_str = payload.file_path.clone();
-    let current_user_id = payload.user_id.clone();
+    let file_path_str = upload.file_path.clone();
+    let workspace_id = upload.workspace_id.clone();
+    let current_user_id = current.id;
     let current_id_userupload = payload.id_userupload.clone(); 
 
     let stream = async_stream::stream! {
@@ -279,6 +326,7 @@ pub async fn normal_analyze_document_stream(
                     id: None,
                     user_id: current_user_id.clone(),
                     id_userupload: current_id_userupload, // <--- DISIMPAN KE DB
+                    workspace_id,
                     source_file: file_path_str,
                     data: financial_data,
                     created_at: Utc::now(),
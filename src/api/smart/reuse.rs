@@ -0,0 +1,65 @@
+// src/api/smart/reuse.rs
+use axum::response::{sse::{Event, Sse}, IntoResponse, Response};
+use chrono::Utc;
+use std::convert::Infallible;
+
+use crate::core::current_user::CurrentUser;
+use crate::db::AppState;
+use crate::models::financial::FinancialRecord;
+
+// Pakai ulang hasil analisa dari upload dengan isi file identik (sha256 sama) di scope yang sama.
+// Tidak memanggil AI dan tidak memakai kuota analisa. None = belum pernah dianalisa,
+// handler lanjut analisa biasa.
+pub(crate) async fn reuse_existing_analysis(
+    state: &AppState,
+    current: &CurrentUser,
+    upload_id: &str,
+) -> Option<Response> {
+    let upload = state.upload_repo.find_in_scope_by_id(upload_id, &current.scope).await.ok().flatten()?;
+    // File karantina ditolak oleh handler analisa
+    if upload.is_quarantined() {
+        return None;
+    }
+    // Upload lama belum punya hash
+    let sha256 = upload.sha256.as_deref()?;
+
+    let upload_ids = match state.upload_repo.find_ids_by_sha256_in_scope(sha256, &current.scope).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            eprintln!("Database Error: {}", e);
+            return None;
+        }
+    };
+    let existing = match state.financial_repo.find_latest_by_upload_ids(&upload_ids).await {
+        Ok(record) => record?,
+        Err(e) => {
+            eprintln!("Database Error: {}", e);
+            return None;
+        }
+    };
+
+    let record = if existing.id_userupload == upload_id {
+        existing
+    } else {
+        let record = FinancialRecord {
+            id: None,
+            user_id: current.id.clone(),
+            id_userupload: upload_id.to_string(),
+            workspace_id: upload.workspace_id.clone(),
+            source_file: upload.file_path.clone(),
+            data: existing.data,
+            created_at: Utc::now(),
+        };
+        if let Err(e) = state.financial_repo.save(record.clone()).await {
+            eprintln!("❌ [DB] Error: {}", e);
+            return None;
+        }
+        record
+    };
+
+    let saved_json = serde_json::to_string(&record).unwrap_or_default();
+    Some(Sse::new(futures::stream::iter(vec![
+        Ok::<Event, Infallible>(Event::default().event("final_result").data(saved_json)),
+        Ok::<Event, Infallible>(Event::default().event("status").data("REUSED_DB")),
+    ])).into_response())
+}
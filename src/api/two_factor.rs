@@ -0,0 +1,168 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use bcrypt::verify;
+use chrono::Utc;
+use serde_json::json;
+use std::sync::Arc;
+use crate::core::auth_utils::hash_token;
+use crate::core::current_user::CurrentUser;
+use crate::core::totp;
+use crate::db::AppState;
+use crate::models::user::{DisableTotpRequest, TotpCodeRequest, User};
+
+fn db_error(e: mongodb::error::Error) -> Response {
+    eprintln!("Database Error: {}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "Database Error" }))).into_response()
+}
+
+fn invalid_code() -> Response {
+    (StatusCode::UNAUTHORIZED, Json(json!({ "error": "Invalid 2FA code" }))).into_response()
+}
+
+// Buat recovery code baru: plaintext dikembalikan ke user sekali, yang disimpan hanya hash-nya
+fn new_recovery_codes() -> (Vec<String>, Vec<String>) {
+    let codes = totp::generate_recovery_codes();
+    let hashes = codes.iter().map(|c| hash_token(c)).collect();
+    (codes, hashes)
+}
+
+// Cek kode TOTP (sekali pakai per time step) atau recovery code (sekali pakai).
+// Dipakai juga oleh langkah kedua login.
+pub(crate) async fn verify_second_factor(state: &AppState, user_id: &str, user: &User, code: &str) -> Result<bool, mongodb::error::Error> {
+    let Some(secret) = user.totp_secret.as_deref() else { return Ok(false) };
+
+    if let Some(step) = totp::verify_code(secret, code, Utc::now().timestamp()) {
+        // Cek cepat dari data user yang sudah dimuat; klaim atomik di DB tetap penentu akhirnya
+        if !totp::is_unused_step(user.totp_last_step, step) {
+            return Ok(false);
+        }
+        return state.user_repo.claim_totp_step(user_id, step).await;
+    }
+
+    let recovery_hash = hash_token(&totp::normalize_recovery_code(code));
+    state.user_repo.consume_recovery_code(user_id, &recovery_hash).await
+}
+
+// --- POST /auth/2fa/setup: buat secret baru + otpauth URI untuk QR code ---
+pub async fn setup_totp(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+    if current.user.totp_enabled {
+        return (StatusCode::CONFLICT, Json(json!({ "error": "2FA is already enabled" }))).into_response();
+    }
+
+    let secret = totp::generate_secret();
+    if let Err(e) = state.user_repo.set_totp_secret(&current.id, &secret).await {
+        return db_error(e);
+    }
+
+    (StatusCode::OK, Json(json!({
+        "status": "success",
+        "secret": secret,
+        "otpauth_uri": totp::otpauth_uri(&current.user.email, &secret),
+    }))).into_response()
+}
+
+// --- POST /auth/2fa/enable: aktifkan setelah kode pertama dari authenticator benar ---
+pub async fn enable_totp(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Json(payload): Json<TotpCodeRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+    if current.user.totp_enabled {
+        return (StatusCode::CONFLICT, Json(json!({ "error": "2FA is already enabled" }))).into_response();
+    }
+    let Some(secret) = current.user.totp_secret.as_deref() else {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Call /auth/2fa/setup first" }))).into_response();
+    };
+
+    let Some(step) = totp::verify_code(secret, &payload.code, Utc::now().timestamp()) else {
+        return invalid_code();
+    };
+    match state.user_repo.claim_totp_step(&current.id, step).await {
+        Ok(true) => {}
+        Ok(false) => return invalid_code(),
+        Err(e) => return db_error(e),
+    }
+
+    let (codes, hashes) = new_recovery_codes();
+    if let Err(e) = state.user_repo.enable_totp(&current.id, hashes).await {
+        return db_error(e);
+    }
+
+    (StatusCode::OK, Json(json!({
+        "status": "success",
+        "message": "2FA enabled. Store these recovery codes safely, they will not be shown again.",
+        "recovery_codes": codes,
+    }))).into_response()
+}
+
+// --- POST /auth/2fa/disable: butuh password + kode 2FA ---
+pub async fn disable_totp(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Json(payload): Json<DisableTotpRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+    if !current.user.totp_enabled {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "2FA is not enabled" }))).into_response();
+    }
+
+    let password_hash = current.user.password.clone();
+    let password_ok = tokio::task::spawn_blocking(move || {
+        verify(payload.password, &password_hash).unwrap_or(false)
+    }).await.unwrap();
+    if !password_ok {
+        return (StatusCode::UNAUTHORIZED, Json(json!({ "error": "Invalid password" }))).into_response();
+    }
+
+    match verify_second_factor(&state, &current.id, &current.user, &payload.code).await {
+        Ok(true) => {}
+        Ok(false) => return invalid_code(),
+        Err(e) => return db_error(e),
+    }
+
+    match state.user_repo.disable_totp(&current.id).await {
+        Ok(_) => (StatusCode::OK, Json(json!({ "status": "success", "message": "2FA disabled" }))).into_response(),
+        Err(e) => db_error(e),
+    }
+}
+
+// --- POST /auth/2fa/recovery-codes: ganti semua recovery code (yang lama tidak berlaku) ---
+pub async fn regenerate_recovery_codes(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Json(payload): Json<TotpCodeRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+    if !current.user.totp_enabled {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "2FA is not enabled" }))).into_response();
+    }
+
+    match verify_second_factor(&state, &current.id, &current.user, &payload.code).await {
+        Ok(true) => {}
+        Ok(false) => return invalid_code(),
+        Err(e) => return db_error(e),
+    }
+
+    let (codes, hashes) = new_recovery_codes();
+    match state.user_repo.set_recovery_codes(&current.id, hashes).await {
+        Ok(_) => (StatusCode::OK, Json(json!({ "status": "success", "recovery_codes": codes }))).into_response(),
+        Err(e) => db_error(e),
+    }
+}
@@ -1,154 +1,1118 @@
 use axum::{
-    extract::{Multipart, State, Path, Query}, // Path di sini adalah axum::extract::Path
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{multipart::Field, Multipart, Query, State, Path}, // Path di sini adalah axum::extract::Path
+    http::{header::CONTENT_LENGTH, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
-use serde::Deserialize;
 use serde_json::json;
 use tokio::fs::{File, create_dir_all, remove_file};
 use tokio::io::AsyncWriteExt;
-use std::path::{Path as StdPath}; // Kita rename Path standar jadi StdPath biar gak bentrok
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
 use std::sync::Arc;
-use chrono::{Local, Utc};
+use std::time::Duration;
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use mongodb::bson::doc;
+use mongodb::bson::Document;
+use crate::core::current_user::CurrentUser;
+use crate::core::file_sniff::{self, FileFormat, SniffError};
+use crate::core::media_path;
+use crate::core::plans;
 use crate::db::AppState;
-use crate::models::upload::UserUpload;
+use crate::models::api_key::{SCOPE_READ, SCOPE_UPLOAD};
+use crate::models::upload::{
+    ListUploadsQuery, UpdateUploadRequest, UploadCursor, UploadListOptions, UploadSort, UserUpload,
+    ANALYSIS_ANALYZED, ANALYSIS_PENDING, SCAN_CLEAN, SCAN_INFECTED,
+};
+use crate::services::archive::{extract_archive, ArchiveLimits};
+use crate::services::cascade;
+use crate::services::trash;
+use crate::services::scanner::{ScanError, ScanVerdict};
+use crate::services::storage::{new_staging_path, StagedFile, StorageError, STAGING_ROOT};
+
+// Masa berlaku presigned URL dari GET /upload/:id/url
+const DOWNLOAD_URL_TTL: Duration = Duration::from_secs(15 * 60);
+
+// Batas metadata upload dari user
+const MAX_ORIGINAL_NAME_LEN: usize = 255;
+const MAX_TITLE_LEN: usize = 200;
+const MAX_NOTES_LEN: usize = 2000;
+const MAX_TAGS: usize = 20;
+const MAX_TAG_LEN: usize = 50;
+
+// Jumlah field "file" per request POST /upload/batch (isi ZIP tidak dihitung, lihat ZIP_MAX_ENTRIES)
+const MAX_BATCH_FILES: usize = 20;
+
+// File yang sudah tersimpan di storage dengan key {user_id}/{file_type}/{nama}
+pub(crate) struct StoredMedia {
+    pub safe_name: String,
+    pub public_url: String,
+    pub size_bytes: u64,
+    pub format: FileFormat,
+    pub sha256: String,
+    pub deduplicated: bool, // true = file identik sudah ada, tidak ditulis ulang
+    pub scan: ScanVerdict,  // Infected = file ada di folder karantina, bukan storage content-addressed
+}
+
+// 415 untuk format tidak dikenal, di luar allowlist, atau ekstensi tidak sesuai isi
+fn unsupported_file(e: SniffError) -> Response {
+    (StatusCode::UNSUPPORTED_MEDIA_TYPE, Json(json!({
+        "error": e.to_string(),
+        "allowed_formats": file_sniff::allowed_formats(),
+    }))).into_response()
+}
+
+fn bad_request(message: String) -> Response {
+    (StatusCode::BAD_REQUEST, Json(json!({ "error": message }))).into_response()
+}
+
+// Nama file asli untuk ditampilkan: tanpa folder (client Windows bisa mengirim "C:\...\x.xlsx")
+// dan tanpa karakter kontrol. Tidak pernah dipakai sebagai path.
+fn display_file_name(raw: &str) -> Option<String> {
+    let base = raw.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = base.chars().filter(|c| !c.is_control()).take(MAX_ORIGINAL_NAME_LEN).collect();
+    let name = name.trim().to_string();
+    (!name.is_empty()).then_some(name)
+}
+
+// Trim, "" dianggap menghapus nilai, tolak jika terlalu panjang
+fn clean_text(field: &str, value: Option<Option<String>>, max_len: usize) -> Result<Option<Option<String>>, Response> {
+    let Some(value) = value else { return Ok(None) };
+    let value = value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    if let Some(v) = &value {
+        if v.chars().count() > max_len {
+            return Err(bad_request(format!("'{}' is too long ({} characters max)", field, max_len)));
+        }
+    }
+    Ok(Some(value))
+}
+
+// Validasi & normalisasi metadata dari form upload maupun PATCH /upload/:id.
+// Tag di-trim, lowercase, dan duplikat dibuang.
+fn normalize_metadata(req: UpdateUploadRequest) -> Result<UpdateUploadRequest, Response> {
+    if let Some(Some(year)) = req.fiscal_year {
+        if !(1900..=2100).contains(&year) {
+            return Err(bad_request(format!("Invalid fiscal_year {}", year)));
+        }
+    }
+
+    let tags = match req.tags {
+        None => None,
+        Some(raw) => {
+            let mut tags: Vec<String> = Vec::new();
+            for tag in raw {
+                let tag = tag.trim().to_lowercase();
+                if tag.is_empty() || tags.contains(&tag) {
+                    continue;
+                }
+                if tag.chars().count() > MAX_TAG_LEN {
+                    return Err(bad_request(format!("Tag '{}' is too long ({} characters max)", tag, MAX_TAG_LEN)));
+                }
+                tags.push(tag);
+            }
+            if tags.len() > MAX_TAGS {
+                return Err(bad_request(format!("Too many tags ({} max)", MAX_TAGS)));
+            }
+            Some(tags)
+        }
+    };
+
+    Ok(UpdateUploadRequest {
+        title: clean_text("title", req.title, MAX_TITLE_LEN)?,
+        company: clean_text("company", req.company, MAX_TITLE_LEN)?,
+        fiscal_year: req.fiscal_year,
+        tags,
+        notes: clean_text("notes", req.notes, MAX_NOTES_LEN)?,
+    })
+}
+
+// Field teks multipart (metadata di form upload)
+async fn read_text_field(field: Field<'_>) -> Result<String, Response> {
+    field.text().await.map_err(|e| (e.status(), e.body_text()).into_response())
+}
+
+// Field metadata di form upload (title, company, fiscal_year, tags, notes); field lain diabaikan
+async fn read_metadata_field(form: &mut UpdateUploadRequest, name: &str, field: Field<'_>) -> Result<(), Response> {
+    match name {
+        "title" => form.title = Some(Some(read_text_field(field).await?)),
+        "company" => form.company = Some(Some(read_text_field(field).await?)),
+        "notes" => form.notes = Some(Some(read_text_field(field).await?)),
+        // Tag dipisah koma, contoh: "audit,2024"
+        "tags" => {
+            let text = read_text_field(field).await?;
+            form.tags = Some(text.split(',').map(str::to_string).collect());
+        }
+        "fiscal_year" => {
+            let text = read_text_field(field).await?;
+            form.fiscal_year = match text.trim() {
+                "" => None,
+                year => Some(Some(year.parse::<i32>()
+                    .map_err(|_| bad_request(format!("Invalid fiscal_year '{}'", year)))?)),
+            };
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// Record UserUpload untuk file yang sudah tersimpan di storage
+fn new_upload_record(
+    current: &CurrentUser,
+    stored: &StoredMedia,
+    original_name: Option<String>,
+    metadata: &UpdateUploadRequest,
+) -> UserUpload {
+    let format = stored.format;
+    UserUpload {
+        id: None,
+        user_id: current.id.clone(),
+        file_name: stored.safe_name.clone(),
+        file_path: stored.public_url.clone(),
+        file_type: format.file_type().to_string(),
+        format: Some(format.as_str().to_string()),
+        sha256: Some(stored.sha256.clone()),
+        size_bytes: Some(stored.size_bytes as i64),
+        original_name,
+        mime_type: Some(format.mime_type().to_string()),
+        title: metadata.title.clone().flatten(),
+        company: metadata.company.clone().flatten(),
+        fiscal_year: metadata.fiscal_year.flatten(),
+        tags: metadata.tags.clone().unwrap_or_default(),
+        notes: metadata.notes.clone().flatten(),
+        workspace_id: current.scope.workspace_id.clone(),
+        deleted_at: None,
+        scan_status: scan_status(&stored.scan).map(str::to_string),
+        scan_signature: match &stored.scan {
+            ScanVerdict::Infected(signature) => Some(signature.clone()),
+            _ => None,
+        },
+        created_at: Utc::now(),
+    }
+}
+
+// scan_status di response upload; None = scanner nonaktif
+fn scan_status(scan: &ScanVerdict) -> Option<&'static str> {
+    match scan {
+        ScanVerdict::Clean => Some(SCAN_CLEAN),
+        ScanVerdict::Infected(_) => Some(SCAN_INFECTED),
+        ScanVerdict::Skipped => None,
+    }
+}
+
+fn storage_error(e: StorageError) -> Response {
+    eprintln!("Storage Error: {}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "Storage Error" }))).into_response()
+}
+
+// Key storage {user_id}/{folder}/{file_name}
+fn media_key(user_id: &str, folder: &str, file_name: &str) -> Result<String, Response> {
+    media_path::user_key(user_id, folder, file_name)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Path Error: {}", e)).into_response())
+}
+
+// Upload di-stream ke file sementara di STAGING_ROOT, lalu dipindah ke storage
+async fn create_staging_file() -> Result<(File, PathBuf), Response> {
+    if let Err(e) = create_dir_all(STAGING_ROOT).await {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Dir Error: {}", e)).into_response());
+    }
+
+    let tmp_path = new_staging_path();
+
+    let file = File::create(&tmp_path)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("File Error: {}", e)).into_response())?;
+    Ok((file, tmp_path))
+}
+
+fn scanner_unavailable(e: ScanError) -> Response {
+    eprintln!("Scanner Error: {}", e);
+    (StatusCode::SERVICE_UNAVAILABLE, Json(json!({ "error": "Malware scan unavailable" }))).into_response()
+}
+
+// Scanner bekerja pada file: data di memory ditulis dulu ke staging, lalu dipindai
+async fn scan_bytes(state: &AppState, data: &[u8]) -> Result<ScanVerdict, Response> {
+    let (mut file, tmp_path) = create_staging_file().await?;
+    let written = file.write_all(data).await.and(file.flush().await);
+    drop(file);
+
+    let verdict = match written {
+        Ok(()) => state.scanner.scan(&tmp_path).await.map_err(scanner_unavailable),
+        Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Write Error").into_response()),
+    };
+    let _ = remove_file(&tmp_path).await;
+    verdict
+}
+
+// Simpan file kecil yang sudah ada di memory (contoh: avatar profil) ke folder tertentu.
+// Nama berbasis waktu; ekstensi berasal dari format hasil deteksi isi file (oleh pemanggil).
+// Tidak ada record upload untuk dikarantina: file yang ditandai scanner langsung ditolak (422).
+pub(crate) async fn store_media_file(
+    state: &AppState,
+    user_id: &str,
+    folder: &str,
+    format: FileFormat,
+    data: &[u8],
+) -> Result<StoredMedia, Response> {
+    let scan = match scan_bytes(state, data).await? {
+        ScanVerdict::Infected(signature) => {
+            eprintln!("⚠️ [SCAN] File {} user {} ditolak: {}", folder, user_id, signature);
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, Json(json!({
+                "error": "File flagged by malware scan",
+                "signature": signature,
+            }))).into_response());
+        }
+        verdict => verdict,
+    };
+
+    let dt = Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let safe_name = format!("{}_{}.{}", dt, folder, format.extension());
+    let key = media_key(user_id, folder, &safe_name)?;
+
+    state.storage.put(&key, data.to_vec(), format.mime_type()).await.map_err(storage_error)?;
+
+    Ok(StoredMedia {
+        public_url: media_path::public_url_for_key(&key),
+        safe_name,
+        size_bytes: data.len() as u64,
+        format,
+        sha256: hex::encode(Sha256::digest(data)),
+        deduplicated: false,
+        scan,
+    })
+}
+
+// Tulis field multipart per chunk ke file sambil menghitung SHA-256;
+// 413 begitu ukuran melewati batas plan
+async fn write_field_chunks(
+    field: &mut Field<'_>,
+    file: &mut File,
+    hasher: &mut Sha256,
+    plan: plans::Plan,
+    mut written: u64,
+) -> Result<u64, Response> {
+    loop {
+        match field.chunk().await {
+            Ok(Some(chunk)) => {
+                written += chunk.len() as u64;
+                plans::ensure_file_size(plan, written)?;
+                hasher.update(&chunk);
+                if file.write_all(&chunk).await.is_err() {
+                    return Err((StatusCode::INTERNAL_SERVER_ERROR, "Write Error").into_response());
+                }
+            }
+            Ok(None) => return Ok(written),
+            // Termasuk body melebihi DefaultBodyLimit (413)
+            Err(e) => return Err((e.status(), e.body_text()).into_response()),
+        }
+    }
+}
+
+// Baca chunk awal sampai SNIFF_LEN byte (atau file habis) untuk deteksi format
+async fn read_head(field: &mut Field<'_>, plan: plans::Plan) -> Result<Vec<u8>, Response> {
+    let mut head = Vec::with_capacity(file_sniff::SNIFF_LEN);
+    while head.len() < file_sniff::SNIFF_LEN {
+        match field.chunk().await {
+            Ok(Some(chunk)) => {
+                head.extend_from_slice(&chunk);
+                plans::ensure_file_size(plan, head.len() as u64)?;
+            }
+            Ok(None) => break,
+            Err(e) => return Err((e.status(), e.body_text()).into_response()),
+        }
+    }
+    Ok(head)
+}
+
+// Stream sisa field multipart (setelah `head`) ke file staging sambil menghitung SHA-256,
+// tanpa menampung seluruh file di memory
+async fn stage_media_field(
+    field: &mut Field<'_>,
+    head: &[u8],
+    format: FileFormat,
+    plan: plans::Plan,
+) -> Result<StagedFile, Response> {
+    let (mut file, tmp_path) = create_staging_file().await?;
+
+    let mut hasher = Sha256::new();
+    let written = async {
+        hasher.update(head);
+        if file.write_all(head).await.is_err() {
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Write Error").into_response());
+        }
+        let size = write_field_chunks(field, &mut file, &mut hasher, plan, head.len() as u64).await?;
+        if file.flush().await.is_err() {
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Write Error").into_response());
+        }
+        Ok(size)
+    }.await;
+    drop(file);
+
+    match written {
+        Ok(size_bytes) => Ok(StagedFile {
+            path: tmp_path,
+            size_bytes,
+            sha256: hex::encode(hasher.finalize()),
+            format,
+        }),
+        Err(resp) => {
+            let _ = remove_file(&tmp_path).await;
+            Err(resp)
+        }
+    }
+}
+
+// File yang ditandai scanner dipindah ke {user_id}/quarantine/ dengan nama unik (tanpa media_blobs),
+// sehingga bisa dihapus bersama upload-nya tanpa menyentuh file bersih dengan isi yang sama
+async fn quarantine_staged_file(
+    state: &AppState,
+    user_id: &str,
+    staged: StagedFile,
+    signature: String,
+) -> Result<StoredMedia, Response> {
+    let StagedFile { path: tmp_path, size_bytes, sha256, format } = staged;
+    eprintln!("⚠️ [SCAN] Upload user {} ({}) dikarantina: {}", user_id, sha256, signature);
+
+    let dt = Local::now().format("%Y%m%d_%H%M%S%6f").to_string();
+    let safe_name = format!("{}_{}.{}", dt, &sha256[..16], format.extension());
+    let key = match media_key(user_id, media_path::QUARANTINE_FOLDER, &safe_name) {
+        Ok(key) => key,
+        Err(resp) => {
+            let _ = remove_file(&tmp_path).await;
+            return Err(resp);
+        }
+    };
+
+    let stored = state.storage.put_file(&key, &tmp_path, format.mime_type()).await;
+    let _ = remove_file(&tmp_path).await;
+    stored.map_err(storage_error)?;
+
+    Ok(StoredMedia {
+        safe_name,
+        public_url: media_path::public_url_for_key(&key),
+        size_bytes,
+        format,
+        sha256,
+        deduplicated: false,
+        scan: ScanVerdict::Infected(signature),
+    })
+}
+
+// Pindai file staging lalu pindahkan ke storage content-addressed dengan key {user_id}/{file_type}/{sha256}.{ext}:
+// isi identik hanya disimpan sekali (ref count di media_blobs). File staging selalu dibersihkan.
+// Scanner tidak bisa dihubungi = upload ditolak (503), file tidak pernah masuk storage tanpa dipindai.
+pub(crate) async fn commit_staged_file(
+    state: &AppState,
+    user_id: &str,
+    staged: StagedFile,
+) -> Result<StoredMedia, Response> {
+    let scan = match state.scanner.scan(&staged.path).await {
+        Ok(ScanVerdict::Infected(signature)) => return quarantine_staged_file(state, user_id, staged, signature).await,
+        Ok(verdict) => verdict,
+        Err(e) => {
+            let _ = remove_file(&staged.path).await;
+            return Err(scanner_unavailable(e));
+        }
+    };
+
+    let StagedFile { path: tmp_path, size_bytes, sha256, format } = staged;
+
+    let safe_name = format!("{}.{}", sha256, format.extension());
+    let key = match media_key(user_id, format.file_type(), &safe_name) {
+        Ok(key) => key,
+        Err(resp) => {
+            let _ = remove_file(&tmp_path).await;
+            return Err(resp);
+        }
+    };
+    let public_url = media_path::public_url_for_key(&key);
+
+    // Referensi dicatat sebelum file final ditulis, supaya release dari upload lain
+    // tidak menghapus file yang baru saja dipakai ulang
+    let ref_count = match state.media_blob_repo.acquire(&public_url, user_id, &sha256, size_bytes as i64).await {
+        Ok(count) => count,
+        Err(e) => {
+            eprintln!("Media Blob Error: {}", e);
+            let _ = remove_file(&tmp_path).await;
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "Database Error" }))).into_response());
+        }
+    };
+
+    let deduplicated = ref_count > 1 && state.storage.exists(&key).await.unwrap_or(false);
+    if deduplicated {
+        let _ = remove_file(&tmp_path).await;
+    } else if let Err(e) = state.storage.put_file(&key, &tmp_path, format.mime_type()).await {
+        let _ = remove_file(&tmp_path).await;
+        if let Err(e) = state.media_blob_repo.release(&public_url).await {
+            eprintln!("Media Blob Error: {}", e);
+        }
+        return Err(storage_error(e));
+    }
+
+    Ok(StoredMedia {
+        safe_name,
+        public_url,
+        size_bytes,
+        format,
+        sha256,
+        deduplicated,
+        scan,
+    })
+}
+
+// Simpan satu field multipart: format dideteksi dari byte awal, lalu di-stream ke staging
+// dan dipindah ke storage (lihat commit_staged_file)
+pub(crate) async fn store_media_field(
+    state: &AppState,
+    user_id: &str,
+    original_name: &str,
+    field: &mut Field<'_>,
+    plan: plans::Plan,
+) -> Result<StoredMedia, Response> {
+    let head = read_head(field, plan).await?;
+    if head.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "No file provided").into_response());
+    }
+    let format = file_sniff::detect_and_validate(&head, original_name).map_err(unsupported_file)?;
+
+    let staged = stage_media_field(field, &head, format, plan).await?;
+    commit_staged_file(state, user_id, staged).await
+}
+
+async fn delete_media(state: &AppState, public_url: &str) {
+    match media_path::public_url_to_key(public_url) {
+        Ok(key) => {
+            if let Err(e) = state.storage.delete(&key).await {
+                eprintln!("Warning: File fisik gagal dihapus: {}", e);
+            }
+        }
+        Err(e) => eprintln!("Warning: Path file tidak valid, file fisik tidak dihapus: {}", e),
+    }
+}
+
+// Lepas satu referensi file content-addressed; file dihapus dari storage saat referensi terakhir dilepas.
+// File karantina tidak punya record blob, jadi langsung dihapus.
+async fn release_blob(state: &AppState, public_url: &str) {
+    match state.media_blob_repo.release(public_url).await {
+        Ok(true) => delete_media(state, public_url).await,
+        Ok(false) => {}
+        Err(e) => eprintln!("Media Blob Error: {}", e),
+    }
+}
 
 // --- 1. Endpoint Upload File ---
 pub async fn upload_file(
     State(state): State<Arc<AppState>>, 
+    current: CurrentUser,
+    headers: HeaderMap,
     mut multipart: Multipart
 ) -> impl IntoResponse {
-    
-    let mut user_id = String::from("unknown");
-    let mut file_data = Vec::new();
-    let mut file_name = String::new();
-    let mut content_type_folder = String::from("others");
-
-    // Parsing Multipart
-    while let Ok(Some(field)) = multipart.next_field().await {
-        let name = field.name().unwrap_or("").to_string();
-
-        match name.as_str() {
-            "user_id" => {
-                user_id = field.text().await.unwrap_or_else(|_| "unknown".into());
-            }
-            "file" => {
-                let mime = field.content_type().unwrap_or("application/octet-stream").to_string();
-                
-                content_type_folder = if mime.starts_with("image/") { "images".into() } 
-                                      else if mime.contains("pdf") { "documents".into() } 
-                                      else { "others".into() };
-
-                file_name = field.file_name().unwrap_or("unnamed").to_string();
-                file_data = field.bytes().await.unwrap_or_default().to_vec();
+    if let Err(resp) = current.require_scope(SCOPE_UPLOAD) {
+        return resp;
+    }
+    if let Err(resp) = current.require_write() {
+        return resp;
+    }
+
+    // Cek kuota upload bulanan plan user
+    let plan = current.plan();
+    let content_length = headers
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if let Err(resp) = plans::ensure_request_size(plan, content_length) {
+        return resp;
+    }
+    if let Err(resp) = plans::ensure_upload_quota(&state, &current.id, plan).await {
+        return resp;
+    }
+
+    // user_id selalu dari sesi, field "user_id" kiriman client diabaikan
+    let user_id = current.id.clone();
+    let mut stored = None;
+    let mut original_name = None;
+    let mut form = UpdateUploadRequest::default();
+
+    // Parsing Multipart: field "file" di-stream langsung ke storage, field lain = metadata opsional
+    let parsed: Result<(), Response> = async {
+        loop {
+            let mut field = match multipart.next_field().await {
+                Ok(Some(field)) => field,
+                Ok(None) => return Ok(()),
+                Err(e) => return Err((e.status(), e.body_text()).into_response()),
+            };
+            let name = field.name().unwrap_or("").to_string();
+
+            match name.as_str() {
+                // Satu file per request, field "file" berikutnya diabaikan
+                "file" if stored.is_none() => {
+                    // MIME kiriman client diabaikan, format ditentukan dari isi file
+                    let file_name = field.file_name().unwrap_or("unnamed").to_string();
+                    stored = Some(store_media_field(&state, &user_id, &file_name, &mut field, plan).await?);
+                    original_name = display_file_name(&file_name);
+                }
+                _ => read_metadata_field(&mut form, &name, field).await?,
             }
-            _ => {}
         }
+    }.await;
+
+    // File yang sudah tersimpan dilepas lagi jika request gagal di tengah jalan
+    if let Err(resp) = parsed {
+        if let Some(saved) = &stored {
+            release_blob(&state, &saved.public_url).await;
+        }
+        return resp;
     }
 
-    if file_data.is_empty() {
+    let Some(stored) = stored else {
         return (StatusCode::BAD_REQUEST, "No file provided").into_response();
+    };
+    let metadata = match normalize_metadata(form) {
+        Ok(metadata) => metadata,
+        Err(resp) => {
+            release_blob(&state, &stored.public_url).await;
+            return resp;
+        }
+    };
+
+    // Kuota dipakai atomik sebelum upload dicatat; request paralel yang lolos cek awal ditolak di sini
+    if let Err(resp) = plans::consume_upload_quota(&state, &user_id, plan).await {
+        release_blob(&state, &stored.public_url).await;
+        return resp;
     }
 
-    // Simpan File Fisik
-    // Gunakan StdPath untuk manipulasi path file sistem
-    let extension = StdPath::new(&file_name).extension().and_then(|ext| ext.to_str()).unwrap_or(""); 
-    let dt = Local::now().format("%Y%m%d_%H%M%S").to_string();
-    
-    let safe_name = if extension.is_empty() {
-        format!("{}_{}", dt, content_type_folder)
-    } else {
-        format!("{}_{}.{}", dt, content_type_folder, extension)
+    // Simpan Metadata ke MongoDB
+    let new_upload = new_upload_record(&current, &stored, original_name.clone(), &metadata);
+
+    let response_metadata = json!({
+        "title": new_upload.title,
+        "company": new_upload.company,
+        "fiscal_year": new_upload.fiscal_year,
+        "tags": new_upload.tags,
+        "notes": new_upload.notes,
+    });
+
+    let upload_id = match state.upload_repo.create_upload(new_upload).await {
+        Ok(id) => Some(id),
+        Err(e) => {
+            eprintln!("Database Error: {}", e);
+            // Tetap return OK karena file fisik tersimpan
+            None
+        }
     };
 
-    let upload_path = format!("media/{}/{}", user_id, content_type_folder);
-    
-    if let Err(e) = create_dir_all(&upload_path).await {
-        return (StatusCode::INTERNAL_SERVER_ERROR, format!("Dir Error: {}", e)).into_response();
+    let StoredMedia { safe_name, public_url, size_bytes, format, sha256, deduplicated, scan } = stored;
+
+    // Upload tetap tercatat (status terlihat di daftar upload), tapi file dikarantina
+    if let ScanVerdict::Infected(signature) = scan {
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({
+            "status": "quarantined",
+            "error": "File flagged by malware scan",
+            "id": upload_id,
+            "original_name": original_name,
+            "scan_status": SCAN_INFECTED,
+            "signature": signature,
+        }))).into_response();
     }
 
-    let full_path = StdPath::new(&upload_path).join(&safe_name);
-    let mut file = match File::create(&full_path).await {
-        Ok(f) => f,
-        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, format!("File Error: {}", e)).into_response(),
+    (StatusCode::OK, Json(json!({
+        "status": "success",
+        "id": upload_id,
+        "saved_as": safe_name,
+        "original_name": original_name,
+        "url": public_url,
+        "type": format.file_type(),
+        "format": format.as_str(),
+        "mime_type": format.mime_type(),
+        "size_bytes": size_bytes,
+        "sha256": sha256,
+        "deduplicated": deduplicated,
+        "scan_status": scan_status(&scan),
+        "metadata": response_metadata
+    }))).into_response()
+}
+
+// Hasil satu file di POST /upload/batch (termasuk file hasil ekstraksi ZIP)
+struct BatchFile {
+    original_name: Option<String>,
+    archive: Option<String>, // nama ZIP asal, None = diupload langsung
+    result: Result<StoredMedia, (StatusCode, String)>,
+}
+
+// Pesan error dari response helper (JSON "error"/"message" atau teks biasa) untuk daftar hasil per file
+async fn error_detail(resp: Response) -> (StatusCode, String) {
+    let status = resp.status();
+    let body = axum::body::to_bytes(resp.into_body(), 64 * 1024).await.unwrap_or_default();
+    let message = match serde_json::from_slice::<serde_json::Value>(&body) {
+        Ok(value) => value.get("error")
+            .or_else(|| value.get("message"))
+            .and_then(|m| m.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| value.to_string()),
+        Err(_) => String::from_utf8_lossy(&body).to_string(),
     };
+    (status, message)
+}
 
-    if file.write_all(&file_data).await.is_err() {
-        return (StatusCode::INTERNAL_SERVER_ERROR, "Write Error").into_response();
+// Pakai satu kuota lalu commit file staging; kuota dikembalikan jika commit gagal
+async fn commit_batch_file(
+    state: &AppState,
+    user_id: &str,
+    staged: StagedFile,
+    plan: plans::Plan,
+) -> Result<StoredMedia, Response> {
+    if let Err(resp) = plans::consume_upload_quota(state, user_id, plan).await {
+        let _ = remove_file(&staged.path).await;
+        return Err(resp);
     }
+    let stored = commit_staged_file(state, user_id, staged).await;
+    if stored.is_err() {
+        plans::release_upload_quota(state, user_id).await;
+    }
+    stored
+}
 
-    // URL yang bisa diakses Frontend
-    let public_url = format!("/public/{}/{}/{}", user_id, content_type_folder, safe_name);
+// Ekstrak ZIP di server: setiap dokumen di dalamnya menjadi file tersendiri
+async fn store_archive_field(
+    state: &AppState,
+    user_id: &str,
+    archive_name: Option<String>,
+    field: &mut Field<'_>,
+    head: &[u8],
+    plan: plans::Plan,
+) -> Vec<BatchFile> {
+    let failed = |status: StatusCode, message: String| vec![BatchFile {
+        original_name: archive_name.clone(),
+        archive: None,
+        result: Err((status, message)),
+    }];
 
-    // Simpan Metadata ke MongoDB
-    let new_upload = UserUpload {
-        id: None,
-        user_id: user_id.clone(),
-        file_name: safe_name.clone(),
-        file_path: public_url.clone(),
-        file_type: content_type_folder.clone(),
-        created_at: Utc::now(),
+    let staged = match stage_media_field(field, head, FileFormat::Zip, plan).await {
+        Ok(staged) => staged,
+        Err(resp) => {
+            let (status, message) = error_detail(resp).await;
+            return failed(status, message);
+        }
+    };
+
+    let limits = ArchiveLimits::from_env(plan.limits().max_file_size_bytes);
+    let archive_path = staged.path.clone();
+    let extracted = tokio::task::spawn_blocking(move || extract_archive(&archive_path, &limits)).await;
+    let _ = remove_file(&staged.path).await;
+
+    let entries = match extracted {
+        Ok(Ok(entries)) => entries,
+        Ok(Err(message)) => return failed(StatusCode::UNPROCESSABLE_ENTITY, message),
+        Err(e) => {
+            eprintln!("Archive Error: {}", e);
+            return failed(StatusCode::INTERNAL_SERVER_ERROR, "Archive Error".to_string());
+        }
+    };
+    if entries.is_empty() {
+        return failed(StatusCode::UNPROCESSABLE_ENTITY, "Archive contains no files".to_string());
+    }
+
+    let mut results = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let result = match entry.result {
+            Ok(staged) => match commit_batch_file(state, user_id, staged, plan).await {
+                Ok(stored) => Ok(stored),
+                Err(resp) => Err(error_detail(resp).await),
+            },
+            Err(message) => Err((StatusCode::UNPROCESSABLE_ENTITY, message)),
+        };
+        results.push(BatchFile {
+            original_name: display_file_name(&entry.name),
+            archive: archive_name.clone(),
+            result,
+        });
+    }
+    results
+}
+
+// Satu field "file" di batch: ZIP diekstrak, format lain disimpan seperti upload biasa
+async fn store_batch_field(
+    state: &AppState,
+    user_id: &str,
+    field: &mut Field<'_>,
+    plan: plans::Plan,
+) -> Vec<BatchFile> {
+    let file_name = field.file_name().unwrap_or("unnamed").to_string();
+    let original_name = display_file_name(&file_name);
+
+    let head = match read_head(field, plan).await {
+        Ok(head) if head.is_empty() => Err((StatusCode::BAD_REQUEST, "No file provided").into_response()),
+        other => other,
+    };
+    let result = match head {
+        Ok(head) if file_sniff::sniff(&head, &file_name) == Some(FileFormat::Zip) => {
+            return store_archive_field(state, user_id, original_name, field, &head, plan).await;
+        }
+        Ok(head) => async {
+            let format = file_sniff::detect_and_validate(&head, &file_name).map_err(unsupported_file)?;
+            let staged = stage_media_field(field, &head, format, plan).await?;
+            commit_batch_file(state, user_id, staged, plan).await
+        }.await,
+        Err(resp) => Err(resp),
+    };
+
+    let result = match result {
+        Ok(stored) => Ok(stored),
+        Err(resp) => Err(error_detail(resp).await),
+    };
+    vec![BatchFile { original_name, archive: None, result }]
+}
+
+// --- POST /upload/batch: banyak file dalam satu request, ZIP diekstrak jadi upload terpisah ---
+// Metadata form (title, company, fiscal_year, tags, notes) berlaku untuk semua file.
+// Gagal per file tidak membatalkan file lain; hasil dikembalikan per file.
+pub async fn upload_batch(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    mut multipart: Multipart
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_scope(SCOPE_UPLOAD) {
+        return resp;
+    }
+    if let Err(resp) = current.require_write() {
+        return resp;
+    }
+
+    let plan = current.plan();
+    if let Err(resp) = plans::ensure_upload_quota(&state, &current.id, plan).await {
+        return resp;
+    }
+
+    let mut files: Vec<BatchFile> = Vec::new();
+    let mut file_fields = 0;
+    let mut form = UpdateUploadRequest::default();
+
+    let parsed: Result<(), Response> = async {
+        loop {
+            let mut field = match multipart.next_field().await {
+                Ok(Some(field)) => field,
+                Ok(None) => return Ok(()),
+                Err(e) => return Err((e.status(), e.body_text()).into_response()),
+            };
+            let name = field.name().unwrap_or("").to_string();
+
+            if name != "file" {
+                read_metadata_field(&mut form, &name, field).await?;
+                continue;
+            }
+
+            file_fields += 1;
+            if file_fields > MAX_BATCH_FILES {
+                files.push(BatchFile {
+                    original_name: field.file_name().and_then(display_file_name),
+                    archive: None,
+                    result: Err((StatusCode::BAD_REQUEST, format!("Too many files in one request ({} max)", MAX_BATCH_FILES))),
+                });
+                continue;
+            }
+            files.extend(store_batch_field(&state, &current.id, &mut field, plan).await);
+        }
+    }.await;
+
+    // Request rusak / metadata tidak valid: semua file yang sudah tersimpan dilepas lagi beserta kuotanya
+    let metadata = parsed.and_then(|_| normalize_metadata(form));
+    let metadata = match metadata {
+        Ok(metadata) if !files.is_empty() => metadata,
+        other => {
+            for file in &files {
+                if let Ok(stored) = &file.result {
+                    release_blob(&state, &stored.public_url).await;
+                    plans::release_upload_quota(&state, &current.id).await;
+                }
+            }
+            return match other {
+                Err(resp) => resp,
+                Ok(_) => (StatusCode::BAD_REQUEST, "No file provided").into_response(),
+            };
+        }
     };
 
-    if let Err(e) = state.upload_repo.create_upload(new_upload).await {
-        eprintln!("Database Error: {}", e);
-        // Tetap return OK karena file fisik tersimpan
+    let mut results = Vec::with_capacity(files.len());
+    let mut uploaded = 0;
+    let mut quarantined = 0;
+    for BatchFile { original_name, archive, result } in files {
+        let stored = match result {
+            Ok(stored) => stored,
+            Err((status, message)) => {
+                results.push(json!({
+                    "status": "error",
+                    "original_name": original_name,
+                    "archive": archive,
+                    "code": status.as_u16(),
+                    "error": message,
+                }));
+                continue;
+            }
+        };
+
+        let new_upload = new_upload_record(&current, &stored, original_name.clone(), &metadata);
+        let upload_id = match state.upload_repo.create_upload(new_upload).await {
+            Ok(id) => id,
+            Err(e) => {
+                eprintln!("Database Error: {}", e);
+                release_blob(&state, &stored.public_url).await;
+                plans::release_upload_quota(&state, &current.id).await;
+                results.push(json!({
+                    "status": "error",
+                    "original_name": original_name,
+                    "archive": archive,
+                    "code": StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                    "error": "Database Error",
+                }));
+                continue;
+            }
+        };
+
+        if let ScanVerdict::Infected(signature) = &stored.scan {
+            quarantined += 1;
+            results.push(json!({
+                "status": "quarantined",
+                "id": upload_id,
+                "original_name": original_name,
+                "archive": archive,
+                "code": StatusCode::UNPROCESSABLE_ENTITY.as_u16(),
+                "error": "File flagged by malware scan",
+                "signature": signature,
+            }));
+            continue;
+        }
+        uploaded += 1;
+
+        results.push(json!({
+            "status": "success",
+            "id": upload_id,
+            "original_name": original_name,
+            "archive": archive,
+            "saved_as": stored.safe_name,
+            "url": stored.public_url,
+            "type": stored.format.file_type(),
+            "format": stored.format.as_str(),
+            "size_bytes": stored.size_bytes,
+            "sha256": stored.sha256,
+            "deduplicated": stored.deduplicated,
+            "scan_status": scan_status(&stored.scan),
+        }));
     }
 
+    // File karantina dihitung gagal: tersimpan, tapi tidak bisa dipakai
+    let failed = results.len() - uploaded;
     (StatusCode::OK, Json(json!({
-        "status": "success",
-        "saved_as": safe_name,
-        "url": public_url,
-        "type": content_type_folder
+        "status": if failed == 0 { "success" } else if uploaded == 0 { "error" } else { "partial" },
+        "uploaded": uploaded,
+        "failed": failed,
+        "quarantined": quarantined,
+        "results": results,
     }))).into_response()
 }
 
-// --- 2. Endpoint Get My Uploads ---
+// Ukuran halaman GET /uploads
+const DEFAULT_PAGE_SIZE: i64 = 20;
+const MAX_PAGE_SIZE: i64 = 100;
 
-#[derive(Deserialize)]
-pub struct UserQuery {
-    pub user_id: String,
+// Tanggal filter: "2024-12-31" (satu hari penuh, UTC) atau RFC 3339.
+// `end_of_day` = batas akhir inklusif untuk parameter "to".
+fn parse_date_param(field: &str, value: &str, end_of_day: bool) -> Result<DateTime<Utc>, Response> {
+    let value = value.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| bad_request(format!("Invalid '{}' date '{}' (use YYYY-MM-DD or RFC 3339)", field, value)))?;
+    let start = date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    Ok(if end_of_day { start + chrono::Duration::days(1) - chrono::Duration::milliseconds(1) } else { start })
 }
 
+// Validasi query GET /uploads; parameter kosong dianggap tidak dikirim
+fn parse_list_query(query: ListUploadsQuery) -> Result<UploadListOptions, Response> {
+    let non_empty = |v: Option<String>| v.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+    // Pagination hanya aktif jika client mengirim limit / cursor; tanpa keduanya semua upload dikembalikan
+    let paginated = query.limit.is_some() || non_empty(query.cursor.clone()).is_some();
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(bad_request(format!("limit must be between 1 and {}", MAX_PAGE_SIZE)));
+    }
+
+    let (sort, descending) = match non_empty(query.sort) {
+        None => (UploadSort::CreatedAt, true),
+        Some(raw) => {
+            let (field, descending) = match raw.strip_prefix('-') {
+                Some(field) => (field.to_string(), true),
+                None => (raw.clone(), false),
+            };
+            let sort = UploadSort::parse(&field)
+                .ok_or_else(|| bad_request(format!("Invalid sort '{}' (created_at, name, size; prefix '-' for descending)", raw)))?;
+            (sort, descending)
+        }
+    };
+
+    let analyzed = match non_empty(query.status).as_deref() {
+        None => None,
+        Some(ANALYSIS_ANALYZED) => Some(true),
+        Some(ANALYSIS_PENDING) => Some(false),
+        Some(other) => return Err(bad_request(format!("Invalid status '{}' (analyzed or pending)", other))),
+    };
+
+    let cursor = match non_empty(query.cursor) {
+        None => None,
+        Some(raw) => Some(UploadCursor::decode(&raw, sort, descending)
+            .ok_or_else(|| bad_request("Invalid cursor for this sort order".to_string()))?),
+    };
+
+    let created_from = non_empty(query.from).map(|v| parse_date_param("from", &v, false)).transpose()?;
+    let created_to = non_empty(query.to).map(|v| parse_date_param("to", &v, true)).transpose()?;
+    if let (Some(from), Some(to)) = (created_from, created_to) {
+        if from > to {
+            return Err(bad_request("'from' must not be after 'to'".to_string()));
+        }
+    }
+
+    let search = non_empty(query.q);
+    if search.as_ref().is_some_and(|q| q.chars().count() > MAX_TITLE_LEN) {
+        return Err(bad_request(format!("'q' is too long ({} characters max)", MAX_TITLE_LEN)));
+    }
+
+    Ok(UploadListOptions {
+        limit: paginated.then_some(limit),
+        cursor,
+        file_type: non_empty(query.file_type).map(|v| v.to_lowercase()),
+        analyzed,
+        // Tag tersimpan lowercase (lihat normalize_metadata)
+        tag: non_empty(query.tag).map(|v| v.to_lowercase()),
+        search,
+        created_from,
+        created_to,
+        sort,
+        descending,
+    })
+}
+
+// --- 2. Endpoint Get My Uploads ---
+// Tanpa limit / cursor: array semua upload (format lama, filter & sort tetap berlaku).
+// Cursor pagination: kirim limit, lalu next_cursor sebagai ?cursor= untuk halaman berikutnya (dengan filter & sort yang sama)
 pub async fn get_my_uploads(
     State(state): State<Arc<AppState>>,
-    Query(query): Query<UserQuery>
+    current: CurrentUser,
+    Query(query): Query<ListUploadsQuery>,
 ) -> impl IntoResponse {
-    match state.upload_repo.find_by_user(&query.user_id).await {
-        Ok(uploads) => (StatusCode::OK, Json(uploads)).into_response(),
+    if let Err(resp) = current.require_scope(SCOPE_READ) {
+        return resp;
+    }
+
+    let options = match parse_list_query(query) {
+        Ok(options) => options,
+        Err(resp) => return resp,
+    };
+
+    match state.upload_repo.list_in_scope(&current.scope, &options).await {
+        Ok((uploads, _)) if options.limit.is_none() => (StatusCode::OK, Json(uploads)).into_response(),
+        Ok((uploads, next_cursor)) => (StatusCode::OK, Json(json!({
+            "status": "success",
+            "data": uploads,
+            "next_cursor": next_cursor,
+            "has_more": next_cursor.is_some(),
+        }))).into_response(),
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("DB Error: {}", e)).into_response(),
     }
 }
 
-pub async fn get_upload_count(
+// --- PATCH /upload/:id: ubah metadata (title, company, fiscal_year, tags, notes) ---
+pub async fn update_upload(
     State(state): State<Arc<AppState>>,
-    Query(query): Query<UserQuery>
+    current: CurrentUser,
+    Path(id): Path<String>,
+    Json(payload): Json<UpdateUploadRequest>,
 ) -> impl IntoResponse {
-    match state.upload_repo.count_by_user(&query.user_id).await {
-        Ok(total) => {
-            (StatusCode::OK, Json(serde_json::json!({
-                "status": "success",
-                "total_uploads": total
-            }))).into_response()
-        },
-        Err(e) => {
-            eprintln!("Database Error: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR, 
-                Json(serde_json::json!({"status": "error", "message": "Failed to count documents"}))
-            ).into_response()
+    if let Err(resp) = current.require_scope(SCOPE_UPLOAD) {
+        return resp;
+    }
+    if let Err(resp) = current.require_write() {
+        return resp;
+    }
+
+    let changes = match normalize_metadata(payload) {
+        Ok(changes) => changes,
+        Err(resp) => return resp,
+    };
+
+    let mut set = Document::new();
+    let mut unset = Document::new();
+    for (key, value) in [("title", changes.title), ("company", changes.company), ("notes", changes.notes)] {
+        match value {
+            Some(Some(v)) => { set.insert(key, v); }
+            Some(None) => { unset.insert(key, ""); }
+            None => {}
         }
     }
+    match changes.fiscal_year {
+        Some(Some(year)) => { set.insert("fiscal_year", year); }
+        Some(None) => { unset.insert("fiscal_year", ""); }
+        None => {}
+    }
+    match changes.tags {
+        Some(tags) if tags.is_empty() => { unset.insert("tags", ""); }
+        Some(tags) => { set.insert("tags", tags); }
+        None => {}
+    }
+
+    if set.is_empty() && unset.is_empty() {
+        return bad_request("Nothing to update".to_string());
+    }
+
+    match state.upload_repo.update_metadata_in_scope(&id, &current.scope, set, unset).await {
+        Ok(Some(upload)) => (StatusCode::OK, Json(json!({ "status": "success", "data": upload }))).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, Json(json!({"error": "File not found"}))).into_response(),
+        Err(_) => (StatusCode::BAD_REQUEST, Json(json!({"error": "Invalid ID format"}))).into_response(),
+    }
+}
+
+// --- GET /upload/:id/url: link unduh sementara ---
+// Backend S3 memberi presigned URL langsung ke bucket; storage lokal memakai URL /public biasa
+pub async fn get_download_url(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_scope(SCOPE_READ) {
+        return resp;
+    }
+
+    let upload = match state.upload_repo.find_in_scope_by_id(&id, &current.scope).await {
+        Ok(Some(record)) => record,
+        Ok(None) => return (StatusCode::NOT_FOUND, Json(json!({"error": "File not found"}))).into_response(),
+        Err(_) => return (StatusCode::BAD_REQUEST, Json(json!({"error": "Invalid ID format"}))).into_response(),
+    };
+    if upload.is_quarantined() {
+        return (StatusCode::FORBIDDEN, Json(json!({"error": "File quarantined by malware scan"}))).into_response();
+    }
+    let key = match media_path::public_url_to_key(&upload.file_path) {
+        Ok(key) => key,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({"error": e.to_string()}))).into_response(),
+    };
+
+    let presigned = state.storage.presign(&key, DOWNLOAD_URL_TTL);
+    (StatusCode::OK, Json(json!({
+        "status": "success",
+        "url": presigned.clone().unwrap_or(upload.file_path),
+        "presigned": presigned.is_some(),
+        "expires_in": presigned.map(|_| DOWNLOAD_URL_TTL.as_secs()),
+    }))).into_response()
 }
 
 pub async fn get_financial_stats(
     State(state): State<Arc<AppState>>,
-    Query(query): Query<UserQuery> 
+    current: CurrentUser,
 ) -> impl IntoResponse {
+    if let Err(resp) = current.require_scope(SCOPE_READ) {
+        return resp;
+    }
+
     // Panggil fungsi dari repository melalui state
-    match state.upload_repo.get_uploads_stats(&query.user_id).await {
+    match state.upload_repo.get_uploads_stats(&current.scope).await {
         Ok(stats_data) => {
             (StatusCode::OK, Json(json!({
                 "status": "success",
@@ -168,50 +1132,123 @@ pub async fn get_financial_stats(
 
 
     
-    // --- 3. Endpoint Delete File ---
+    // --- 3. Endpoint Delete File (soft delete ke trash) ---
 
 
 
     pub async fn delete_file(
     State(state): State<Arc<AppState>>,
+    current: CurrentUser,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
-    
-    // 1. Cari data upload di database (untuk mendapatkan path file fisik)
-    let upload_record = match state.upload_repo.find_by_id(&id).await {
-        Ok(Some(record)) => record,
-        Ok(None) => return (StatusCode::NOT_FOUND, Json(json!({"error": "File not found"}))).into_response(),
+    if let Err(resp) = current.require_scope(SCOPE_UPLOAD) {
+        return resp;
+    }
+    if let Err(resp) = current.require_write() {
+        return resp;
+    }
+
+    // 1. Pindahkan upload ke trash (file fisik tetap ada sampai di-purge)
+    let deleted_at = Utc::now();
+    match state.upload_repo.soft_delete_in_scope(&id, &current.scope, deleted_at).await {
+        Ok(0) => return (StatusCode::NOT_FOUND, Json(json!({"error": "File not found"}))).into_response(),
+        Ok(_) => {}
         Err(_) => return (StatusCode::BAD_REQUEST, Json(json!({"error": "Invalid ID format"}))).into_response(),
-    };
+    }
 
-    // 2. HAPUS OTOMATIS: Record di financial_reports
-    // Karena id di sini adalah ID dari user_upload, kita gunakan sebagai filter
-    if let Err(e) = state.financial_repo.delete_by_upload_id(&id).await {
-        // Kita log errornya, tapi tetap lanjut menghapus file fisik
-        eprintln!("Error deleting financial records for upload {}: {}", id, e);
+    // 2. Record di financial_reports ikut disembunyikan
+    if let Err(e) = state.financial_repo.set_deleted_by_upload_id(&id, Some(deleted_at)).await {
+        eprintln!("Error trashing financial records for upload {}: {}", id, e);
     }
 
-    // 3. Hapus File Fisik
-    let relative_path = upload_record.file_path.trim_start_matches("/public/");
-    let system_path = StdPath::new("media").join(relative_path);
+    (StatusCode::OK, Json(json!({
+        "status": "success",
+        "message": "Upload moved to trash",
+        "id": id,
+        "purge_at": trash::purge_at(deleted_at),
+    }))).into_response()
+}
 
-    if let Err(e) = remove_file(&system_path).await {
-        eprintln!("Warning: File fisik tidak ditemukan atau gagal dihapus: {}", e);
+// --- GET /uploads/trash: upload yang dihapus, beserta waktu purge otomatis ---
+pub async fn list_trash(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_scope(SCOPE_READ) {
+        return resp;
     }
 
-    // 4. Hapus Record Upload (Induk)
-    match state.upload_repo.delete(&id).await {
-        Ok(count) => {
-            if count > 0 {
-                (StatusCode::OK, Json(json!({
-                    "status": "success",
-                    "message": "File, financial reports, and upload record deleted",
-                    "id": id
-                }))).into_response()
-            } else {
-                (StatusCode::NOT_FOUND, Json(json!({"error": "Upload record already gone"}))).into_response()
-            }
+    match state.upload_repo.find_trash_in_scope(&current.scope).await {
+        Ok(uploads) => {
+            let items: Vec<_> = uploads
+                .into_iter()
+                .map(|upload| {
+                    let purge_at = upload.deleted_at.map(|at| trash::purge_at(at.to_chrono()));
+                    json!({ "upload": upload, "purge_at": purge_at })
+                })
+                .collect();
+            (StatusCode::OK, Json(json!({
+                "status": "success",
+                "retention_days": trash::retention_days(),
+                "data": items,
+            }))).into_response()
         }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("DB Error: {}", e)).into_response(),
+    }
+}
+
+// --- POST /upload/:id/restore: kembalikan upload dari trash ---
+pub async fn restore_upload(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_scope(SCOPE_UPLOAD) {
+        return resp;
+    }
+    if let Err(resp) = current.require_write() {
+        return resp;
+    }
+
+    let upload = match state.upload_repo.restore_in_scope(&id, &current.scope).await {
+        Ok(Some(upload)) => upload,
+        Ok(None) => return (StatusCode::NOT_FOUND, Json(json!({"error": "File not found in trash"}))).into_response(),
+        Err(_) => return (StatusCode::BAD_REQUEST, Json(json!({"error": "Invalid ID format"}))).into_response(),
+    };
+
+    if let Err(e) = state.financial_repo.set_deleted_by_upload_id(&id, None).await {
+        eprintln!("Error restoring financial records for upload {}: {}", id, e);
+    }
+
+    (StatusCode::OK, Json(json!({ "status": "success", "data": upload }))).into_response()
+}
+
+// --- DELETE /uploads/trash/:id: hapus permanen tanpa menunggu masa retensi ---
+pub async fn purge_trashed_upload(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_scope(SCOPE_UPLOAD) {
+        return resp;
+    }
+    if let Err(resp) = current.require_write() {
+        return resp;
+    }
+
+    let upload = match state.upload_repo.find_trash_in_scope_by_id(&id, &current.scope).await {
+        Ok(Some(upload)) => upload,
+        Ok(None) => return (StatusCode::NOT_FOUND, Json(json!({"error": "File not found in trash"}))).into_response(),
+        Err(_) => return (StatusCode::BAD_REQUEST, Json(json!({"error": "Invalid ID format"}))).into_response(),
+    };
+
+    match cascade::delete_upload(&state, &upload, true).await {
+        Ok(true) => (StatusCode::OK, Json(json!({
+            "status": "success",
+            "message": "File, financial reports, and upload record deleted",
+            "id": id
+        }))).into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, Json(json!({"error": "Upload record already gone"}))).into_response(),
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": e.to_string()}))).into_response(),
     }
 }
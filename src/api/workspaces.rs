@@ -0,0 +1,256 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use serde_json::json;
+use std::sync::Arc;
+use crate::core::current_user::CurrentUser;
+use crate::db::AppState;
+use crate::models::workspace::{
+    CreateWorkspaceRequest, InviteMemberRequest, UpdateMemberRequest, Workspace, WorkspaceMember,
+    ROLE_OWNER, WORKSPACE_ROLES,
+};
+
+fn db_error(e: mongodb::error::Error) -> Response {
+    eprintln!("Database Error: {}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "Database Error" }))).into_response()
+}
+
+// Hanya owner workspace yang boleh mengelola member
+async fn require_owner(state: &AppState, workspace_id: &str, user_id: &str) -> Result<Workspace, Response> {
+    let member = state.workspace_repo.find_member(workspace_id, user_id).await.map_err(db_error)?;
+    match member {
+        Some(m) if m.role == ROLE_OWNER => {}
+        Some(_) => return Err((StatusCode::FORBIDDEN, Json(json!({ "error": "Only the workspace owner can manage members" }))).into_response()),
+        None => return Err((StatusCode::NOT_FOUND, Json(json!({ "error": "Workspace not found" }))).into_response()),
+    }
+
+    state.workspace_repo.find_by_id(workspace_id).await
+        .map_err(|_| (StatusCode::BAD_REQUEST, Json(json!({ "error": "Invalid ID format" }))).into_response())?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(json!({ "error": "Workspace not found" }))).into_response())
+}
+
+fn validate_role(role: &str) -> Result<(), Response> {
+    if WORKSPACE_ROLES.contains(&role) {
+        return Ok(());
+    }
+    Err((StatusCode::BAD_REQUEST, Json(json!({
+        "error": format!("Unknown role '{}'", role),
+        "allowed": WORKSPACE_ROLES,
+    }))).into_response())
+}
+
+// --- POST /workspaces: pembuat otomatis menjadi owner ---
+pub async fn create_workspace(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Json(payload): Json<CreateWorkspaceRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+
+    let name = payload.name.trim().to_string();
+    if name.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Name is required" }))).into_response();
+    }
+
+    let now = Utc::now();
+    let workspace_id = match state.workspace_repo.create(Workspace {
+        id: None,
+        name: name.clone(),
+        owner_id: current.id.clone(),
+        created_at: now,
+    }).await {
+        Ok(id) => id,
+        Err(e) => return db_error(e),
+    };
+
+    if let Err(e) = state.workspace_repo.add_member(WorkspaceMember {
+        id: None,
+        workspace_id: workspace_id.clone(),
+        user_id: current.id.clone(),
+        role: ROLE_OWNER.to_string(),
+        created_at: now,
+    }).await {
+        return db_error(e);
+    }
+
+    (StatusCode::CREATED, Json(json!({
+        "status": "success",
+        "id": workspace_id,
+        "name": name,
+        "role": ROLE_OWNER
+    }))).into_response()
+}
+
+// --- GET /workspaces: workspace tempat user menjadi member ---
+pub async fn list_workspaces(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+) -> impl IntoResponse {
+    match state.workspace_repo.list_for_user(&current.id).await {
+        Ok(items) => {
+            let data: Vec<_> = items.into_iter().map(|(ws, role)| json!({
+                "id": ws.id.map(|oid| oid.to_hex()).unwrap_or_default(),
+                "name": ws.name,
+                "owner_id": ws.owner_id,
+                "role": role,
+                "created_at": ws.created_at,
+            })).collect();
+            (StatusCode::OK, Json(json!({ "status": "success", "data": data }))).into_response()
+        }
+        Err(e) => db_error(e),
+    }
+}
+
+// --- GET /workspaces/:id/members ---
+pub async fn list_members(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Path(workspace_id): Path<String>,
+) -> impl IntoResponse {
+    match state.workspace_repo.find_member(&workspace_id, &current.id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return (StatusCode::NOT_FOUND, Json(json!({ "error": "Workspace not found" }))).into_response(),
+        Err(e) => return db_error(e),
+    }
+
+    let members = match state.workspace_repo.list_members(&workspace_id).await {
+        Ok(m) => m,
+        Err(e) => return db_error(e),
+    };
+
+    let mut data = Vec::with_capacity(members.len());
+    for m in members {
+        let user = state.user_repo.find_by_id(&m.user_id).await;
+        data.push(json!({
+            "user_id": m.user_id,
+            "name": user.as_ref().map(|u| u.name.clone()),
+            "email": user.as_ref().map(|u| u.email.clone()),
+            "role": m.role,
+            "joined_at": m.created_at,
+        }));
+    }
+
+    (StatusCode::OK, Json(json!({ "status": "success", "data": data }))).into_response()
+}
+
+// --- POST /workspaces/:id/members: undang user terdaftar berdasarkan email ---
+pub async fn invite_member(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Path(workspace_id): Path<String>,
+    Json(payload): Json<InviteMemberRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+    let workspace = match require_owner(&state, &workspace_id, &current.id).await {
+        Ok(ws) => ws,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = validate_role(&payload.role) {
+        return resp;
+    }
+
+    let Some(invitee) = state.user_repo.find_by_email(&payload.email).await else {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "User not found" }))).into_response();
+    };
+    let invitee_id = invitee.id.map(|oid| oid.to_hex()).unwrap_or_default();
+
+    match state.workspace_repo.find_member(&workspace_id, &invitee_id).await {
+        Ok(Some(_)) => return (StatusCode::CONFLICT, Json(json!({ "error": "User is already a member" }))).into_response(),
+        Ok(None) => {}
+        Err(e) => return db_error(e),
+    }
+
+    if let Err(e) = state.workspace_repo.add_member(WorkspaceMember {
+        id: None,
+        workspace_id: workspace_id.clone(),
+        user_id: invitee_id.clone(),
+        role: payload.role.clone(),
+        created_at: Utc::now(),
+    }).await {
+        return db_error(e);
+    }
+
+    let body = format!(
+        "Halo {},\n\n{} menambahkan Anda ke workspace \"{}\" sebagai {}.\n{}",
+        invitee.name, current.user.name, workspace.name, payload.role, state.app_base_url
+    );
+    if let Err(e) = state.mailer.send(&invitee.email, "Undangan Workspace Kepin", &body).await {
+        eprintln!("Mailer Error: {}", e);
+    }
+
+    (StatusCode::CREATED, Json(json!({
+        "status": "success",
+        "user_id": invitee_id,
+        "role": payload.role
+    }))).into_response()
+}
+
+// --- PATCH /workspaces/:id/members/:user_id ---
+pub async fn update_member(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Path((workspace_id, user_id)): Path<(String, String)>,
+    Json(payload): Json<UpdateMemberRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+    let workspace = match require_owner(&state, &workspace_id, &current.id).await {
+        Ok(ws) => ws,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = validate_role(&payload.role) {
+        return resp;
+    }
+    if user_id == workspace.owner_id {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "The workspace creator must stay owner" }))).into_response();
+    }
+
+    match state.workspace_repo.update_member_role(&workspace_id, &user_id, &payload.role).await {
+        Ok(count) if count > 0 => (StatusCode::OK, Json(json!({ "status": "success", "user_id": user_id, "role": payload.role }))).into_response(),
+        Ok(_) => (StatusCode::NOT_FOUND, Json(json!({ "error": "Member not found" }))).into_response(),
+        Err(e) => db_error(e),
+    }
+}
+
+// --- DELETE /workspaces/:id/members/:user_id: owner mengeluarkan member, atau member keluar sendiri ---
+pub async fn remove_member(
+    State(state): State<Arc<AppState>>,
+    current: CurrentUser,
+    Path((workspace_id, user_id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+
+    let workspace = if user_id == current.id {
+        match state.workspace_repo.find_by_id(&workspace_id).await {
+            Ok(Some(ws)) => ws,
+            Ok(None) => return (StatusCode::NOT_FOUND, Json(json!({ "error": "Workspace not found" }))).into_response(),
+            Err(_) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Invalid ID format" }))).into_response(),
+        }
+    } else {
+        match require_owner(&state, &workspace_id, &current.id).await {
+            Ok(ws) => ws,
+            Err(resp) => return resp,
+        }
+    };
+
+    if user_id == workspace.owner_id {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "The workspace creator cannot be removed" }))).into_response();
+    }
+
+    match state.workspace_repo.remove_member(&workspace_id, &user_id).await {
+        Ok(count) if count > 0 => (StatusCode::OK, Json(json!({ "status": "success", "user_id": user_id }))).into_response(),
+        Ok(_) => (StatusCode::NOT_FOUND, Json(json!({ "error": "Member not found" }))).into_response(),
+        Err(e) => db_error(e),
+    }
+}
@@ -1,5 +1,57 @@
 // src/core/auth_utils.rs
-pub fn create_token(user_id: &str) -> String {
-    // Implementasi JWT di sini
-    format!("token_for_{}", user_id)
-}
\ No newline at end of file
+use chrono::{Duration, Utc};
+use jsonwebtoken::{decode, encode, errors::Error as JwtError, DecodingKey, EncodingKey, Header, Validation};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// Nama cookie yang menyimpan JWT sesi (access token)
+pub const SESSION_COOKIE: &str = "session";
+// Nama cookie refresh token (hanya dikirim ke /api/v1/auth)
+pub const REFRESH_COOKIE: &str = "refresh_token";
+pub const REFRESH_COOKIE_PATH: &str = "/api/v1/auth";
+
+// Access token berumur pendek, refresh token berumur panjang
+pub const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String, // ID User (ObjectId hex)
+    pub sid: String, // ID Session, untuk revoke dari server
+    pub iat: i64,
+    pub exp: i64,
+}
+
+pub fn create_token(user_id: &str, session_id: &str, secret: &str) -> Result<String, JwtError> {
+    let now = Utc::now();
+    let claims = Claims {
+        sub: user_id.to_string(),
+        sid: session_id.to_string(),
+        iat: now.timestamp(),
+        exp: (now + Duration::minutes(ACCESS_TOKEN_TTL_MINUTES)).timestamp(),
+    };
+
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes()))
+}
+
+// Menolak token yang signature-nya salah (tampered) atau sudah expired
+pub fn verify_token(token: &str, secret: &str) -> Result<Claims, JwtError> {
+    let mut validation = Validation::default();
+    validation.leeway = 0;
+
+    decode::<Claims>(token, &DecodingKey::from_secret(secret.as_bytes()), &validation)
+        .map(|data| data.claims)
+}
+
+// Token acak (opaque) untuk refresh token, dikirim ke client apa adanya
+pub fn generate_opaque_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+// Yang disimpan di DB hanya hash SHA-256 dari token
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
@@ -0,0 +1,222 @@
+// src/core/current_user.rs
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use std::sync::Arc;
+use tower_cookies::Cookies;
+
+use crate::core::auth_utils::{hash_token, verify_token, SESSION_COOKIE};
+use crate::core::plans::Plan;
+use crate::db::AppState;
+use crate::models::user::User;
+use crate::models::workspace::Scope;
+
+pub const WORKSPACE_HEADER: &str = "x-workspace-id";
+
+// Cara user terautentikasi pada request ini
+#[derive(Clone)]
+pub enum AuthMethod {
+    Session { session_id: String },
+    ApiKey { scopes: Vec<String> },
+}
+
+// User yang sedang login, di-resolve dari cookie sesi (JWT) atau header
+// "Authorization: Bearer <api_key>". Handler wajib memakai ini, bukan user_id kiriman client.
+#[derive(Clone)]
+pub struct CurrentUser {
+    pub id: String,
+    pub user: User,
+    pub auth: AuthMethod,
+    pub scope: Scope, // data pribadi atau workspace (header X-Workspace-Id)
+}
+
+impl CurrentUser {
+    pub fn plan(&self) -> Plan {
+        Plan::parse(&self.user.plan)
+    }
+
+    pub fn session_id(&self) -> Option<&str> {
+        match &self.auth {
+            AuthMethod::Session { session_id } => Some(session_id),
+            AuthMethod::ApiKey { .. } => None,
+        }
+    }
+
+    // Login via sesi punya akses penuh, API key hanya sesuai scope-nya
+    pub fn require_scope(&self, scope: &str) -> Result<(), Response> {
+        match &self.auth {
+            AuthMethod::Session { .. } => Ok(()),
+            AuthMethod::ApiKey { scopes, .. } if scopes.iter().any(|s| s == scope) => Ok(()),
+            AuthMethod::ApiKey { .. } => Err((
+                StatusCode::FORBIDDEN,
+                Json(json!({ "error": format!("API key lacks scope '{}'", scope) })),
+            ).into_response()),
+        }
+    }
+
+    // Upload, hapus, dan analisa butuh role owner/analyst di scope aktif
+    pub fn require_write(&self) -> Result<(), Response> {
+        if self.scope.can_write() {
+            return Ok(());
+        }
+        Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({ "error": format!("Role '{}' is read-only in this workspace", self.scope.role) })),
+        ).into_response())
+    }
+
+    // Endpoint akun (kelola sesi, API key, dll) tidak boleh diakses via API key
+    pub fn require_session(&self) -> Result<(), Response> {
+        match &self.auth {
+            AuthMethod::Session { .. } => Ok(()),
+            AuthMethod::ApiKey { .. } => Err((
+                StatusCode::FORBIDDEN,
+                Json(json!({ "error": "This endpoint requires a browser session" })),
+            ).into_response()),
+        }
+    }
+}
+
+fn unauthorized(message: &str) -> Response {
+    (StatusCode::UNAUTHORIZED, Json(json!({ "error": message }))).into_response()
+}
+
+async fn from_api_key(key: &str, state: &AppState) -> Result<CurrentUser, Response> {
+    let api_key = state
+        .api_key_repo
+        .find_active_by_hash(&hash_token(key))
+        .await
+        .ok()
+        .flatten()
+        .ok_or_else(|| unauthorized("Invalid API key"))?;
+
+    let key_id = api_key.id.map(|oid| oid.to_hex()).unwrap_or_default();
+    if let Err(e) = state.api_key_repo.touch(&key_id).await {
+        eprintln!("API key touch error: {}", e);
+    }
+
+    let user = state
+        .user_repo
+        .find_by_id(&api_key.user_id)
+        .await
+        .ok_or_else(|| unauthorized("Invalid API key"))?;
+
+    Ok(CurrentUser {
+        scope: Scope::personal(&api_key.user_id),
+        id: api_key.user_id,
+        user,
+        auth: AuthMethod::ApiKey { scopes: api_key.scopes },
+    })
+}
+
+async fn from_session_cookie(token: &str, state: &AppState) -> Result<CurrentUser, Response> {
+    let claims = verify_token(token, &state.jwt_secret)
+        .map_err(|_| unauthorized("Invalid or expired session"))?;
+
+    // JWT valid belum cukup: sesi di server harus masih aktif (belum di-revoke)
+    let session = state
+        .session_repo
+        .find_active(&claims.sid)
+        .await
+        .ok()
+        .flatten()
+        .filter(|s| s.user_id == claims.sub)
+        .ok_or_else(|| unauthorized("Session revoked"))?;
+
+    // Update last_used_at maksimal sekali per menit
+    if Utc::now() - session.last_used_at > Duration::minutes(1) {
+        if let Err(e) = state.session_repo.touch(&claims.sid).await {
+            eprintln!("Session touch error: {}", e);
+        }
+    }
+
+    let user = state
+        .user_repo
+        .find_by_id(&claims.sub)
+        .await
+        .ok_or_else(|| unauthorized("Not logged in"))?;
+
+    Ok(CurrentUser {
+        scope: Scope::personal(&claims.sub),
+        id: claims.sub,
+        user,
+        auth: AuthMethod::Session { session_id: claims.sid },
+    })
+}
+
+// Header X-Workspace-Id memilih workspace; user harus member workspace tersebut
+async fn resolve_scope(parts: &Parts, state: &AppState, user_id: &str) -> Result<Scope, Response> {
+    let Some(header) = parts.headers.get(WORKSPACE_HEADER) else {
+        return Ok(Scope::personal(user_id));
+    };
+
+    let workspace_id = header.to_str().unwrap_or_default().trim();
+    let member = state
+        .workspace_repo
+        .find_member(workspace_id, user_id)
+        .await
+        .ok()
+        .flatten()
+        .ok_or_else(|| (
+            StatusCode::FORBIDDEN,
+            Json(json!({ "error": "Not a member of this workspace" })),
+        ).into_response())?;
+
+    Ok(Scope {
+        user_id: user_id.to_string(),
+        workspace_id: Some(member.workspace_id),
+        role: member.role,
+    })
+}
+
+// API key (Authorization: Bearer) atau cookie sesi, lalu scope workspace
+async fn resolve(parts: &mut Parts, state: &Arc<AppState>) -> Result<CurrentUser, Response> {
+    // 1. API key via header Authorization (script / integrasi)
+    if let Some(header) = parts.headers.get(AUTHORIZATION) {
+        let key = header
+            .to_str()
+            .ok()
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+            .ok_or_else(|| unauthorized("Invalid Authorization header"))?;
+        let mut current = from_api_key(key, state).await?;
+        current.scope = resolve_scope(parts, state, &current.id).await?;
+        return Ok(current);
+    }
+
+    // 2. Cookie sesi (browser)
+    let cookies = Cookies::from_request_parts(parts, state)
+        .await
+        .map_err(|e| e.into_response())?;
+
+    let token = cookies
+        .get(SESSION_COOKIE)
+        .map(|c| c.value().to_string())
+        .ok_or_else(|| unauthorized("Not logged in"))?;
+
+    let mut current = from_session_cookie(&token, state).await?;
+    current.scope = resolve_scope(parts, state, &current.id).await?;
+    Ok(current)
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for CurrentUser {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        // Sudah di-resolve oleh middleware RBAC pada request yang sama
+        if let Some(current) = parts.extensions.get::<CurrentUser>() {
+            return Ok(current.clone());
+        }
+
+        let current = resolve(parts, state).await?;
+        parts.extensions.insert(current.clone());
+        Ok(current)
+    }
+}
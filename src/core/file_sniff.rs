@@ -0,0 +1,320 @@
+// src/core/file_sniff.rs
+// Deteksi format file dari isi (magic bytes), bukan dari MIME / ekstensi kiriman client.
+use std::env;
+use std::path::Path;
+
+// Jumlah byte awal yang dibaca sebelum menentukan format
+pub const SNIFF_LEN: usize = 8 * 1024;
+
+pub const TYPE_PDF: &str = "pdf";
+pub const TYPE_SPREADSHEET: &str = "spreadsheet";
+pub const TYPE_IMAGE: &str = "image";
+pub const TYPE_TEXT: &str = "text";
+pub const TYPE_ARCHIVE: &str = "archive";
+
+// Default jika env ALLOWED_UPLOAD_FORMATS tidak diset
+const DEFAULT_ALLOWED_FORMATS: &str = "pdf,xlsx,xls,csv,png,jpeg,gif,webp";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileFormat {
+    Pdf,
+    Xlsx,
+    Xls, // OLE compound file (Excel 97-2003)
+    Zip,
+    Csv,
+    Txt,
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+}
+
+impl FileFormat {
+    const ALL: [FileFormat; 10] = [
+        FileFormat::Pdf, FileFormat::Xlsx, FileFormat::Xls, FileFormat::Zip, FileFormat::Csv,
+        FileFormat::Txt, FileFormat::Png, FileFormat::Jpeg, FileFormat::Gif, FileFormat::Webp,
+    ];
+
+    // Format dari ekstensi file tersimpan (hasil `extension()`), contoh untuk Content-Type
+    pub fn from_extension(ext: &str) -> Option<FileFormat> {
+        Self::ALL.iter().copied().find(|f| f.extension() == ext)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileFormat::Pdf => "pdf",
+            FileFormat::Xlsx => "xlsx",
+            FileFormat::Xls => "xls",
+            FileFormat::Zip => "zip",
+            FileFormat::Csv => "csv",
+            FileFormat::Txt => "txt",
+            FileFormat::Png => "png",
+            FileFormat::Jpeg => "jpeg",
+            FileFormat::Gif => "gif",
+            FileFormat::Webp => "webp",
+        }
+    }
+
+    // Ekstensi yang dipakai untuk nama file tersimpan
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FileFormat::Jpeg => "jpg",
+            other => other.as_str(),
+        }
+    }
+
+    // Kategori untuk UserUpload.file_type (sekaligus nama folder di media/{user_id}/)
+    pub fn file_type(&self) -> &'static str {
+        match self {
+            FileFormat::Pdf => TYPE_PDF,
+            FileFormat::Xlsx | FileFormat::Xls | FileFormat::Csv => TYPE_SPREADSHEET,
+            FileFormat::Png | FileFormat::Jpeg | FileFormat::Gif | FileFormat::Webp => TYPE_IMAGE,
+            FileFormat::Txt => TYPE_TEXT,
+            FileFormat::Zip => TYPE_ARCHIVE,
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            FileFormat::Pdf => "application/pdf",
+            FileFormat::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            FileFormat::Xls => "application/vnd.ms-excel",
+            FileFormat::Zip => "application/zip",
+            FileFormat::Csv => "text/csv",
+            FileFormat::Txt => "text/plain",
+            FileFormat::Png => "image/png",
+            FileFormat::Jpeg => "image/jpeg",
+            FileFormat::Gif => "image/gif",
+            FileFormat::Webp => "image/webp",
+        }
+    }
+
+    // Ekstensi client yang cocok dengan format ini
+    fn accepts_extension(&self, ext: &str) -> bool {
+        match self {
+            FileFormat::Pdf => ext == "pdf",
+            FileFormat::Xlsx => matches!(ext, "xlsx" | "xlsm"),
+            FileFormat::Xls => ext == "xls",
+            FileFormat::Zip => ext == "zip",
+            FileFormat::Csv => ext == "csv",
+            FileFormat::Txt => matches!(ext, "txt" | "text"),
+            FileFormat::Png => ext == "png",
+            FileFormat::Jpeg => matches!(ext, "jpg" | "jpeg"),
+            FileFormat::Gif => ext == "gif",
+            FileFormat::Webp => ext == "webp",
+        }
+    }
+}
+
+// Format dari byte awal file. `file_name` hanya dipakai untuk membedakan CSV dan teks biasa.
+pub fn sniff(head: &[u8], file_name: &str) -> Option<FileFormat> {
+    if head.starts_with(b"%PDF-") {
+        return Some(FileFormat::Pdf);
+    }
+    if head.starts_with(b"PK\x03\x04") {
+        // XLSX = paket OOXML (ZIP) berisi folder "xl/". Nama entry tersimpan tanpa kompresi
+        // di header lokal, jadi cukup dicari di byte awal.
+        let contains = |needle: &[u8]| head.windows(needle.len()).any(|w| w == needle);
+        let is_xlsx = contains(b"xl/")
+            || (contains(b"[Content_Types].xml") && !contains(b"word/") && !contains(b"ppt/"));
+        return Some(if is_xlsx { FileFormat::Xlsx } else { FileFormat::Zip });
+    }
+    if head.starts_with(&[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1]) {
+        return Some(FileFormat::Xls);
+    }
+    if head.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+        return Some(FileFormat::Png);
+    }
+    if head.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(FileFormat::Jpeg);
+    }
+    if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        return Some(FileFormat::Gif);
+    }
+    if head.len() >= 12 && &head[0..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+        return Some(FileFormat::Webp);
+    }
+
+    if looks_like_text(head) {
+        let is_csv = match client_extension(file_name).as_deref() {
+            Some("csv") => true,
+            Some("txt") | Some("text") => false,
+            _ => looks_like_csv(head),
+        };
+        return Some(if is_csv { FileFormat::Csv } else { FileFormat::Txt });
+    }
+    None
+}
+
+// UTF-8 valid (boleh terpotong di akhir buffer) dan tanpa byte kontrol biner
+fn looks_like_text(head: &[u8]) -> bool {
+    if head.is_empty() {
+        return false;
+    }
+    let valid = match std::str::from_utf8(head) {
+        Ok(_) => true,
+        // Karakter multi-byte terpotong di ujung SNIFF_LEN masih dianggap teks
+        Err(e) => e.error_len().is_none(),
+    };
+    valid && !head.iter().any(|&b| b == 0 || (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r')))
+}
+
+// Baris-baris awal punya jumlah pemisah (, ; atau tab) yang sama
+fn looks_like_csv(head: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(head);
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).take(5).collect();
+    if lines.len() < 2 {
+        return false;
+    }
+    [',', ';', '\t'].iter().any(|sep| {
+        let first = lines[0].matches(*sep).count();
+        first > 0 && lines.iter().all(|l| l.matches(*sep).count() == first)
+    })
+}
+
+pub fn client_extension(file_name: &str) -> Option<String> {
+    Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .filter(|ext| !ext.is_empty())
+}
+
+// Format yang diterima, dari env ALLOWED_UPLOAD_FORMATS (dipisah koma), contoh: "pdf,xlsx,csv"
+pub fn allowed_formats() -> Vec<String> {
+    env::var("ALLOWED_UPLOAD_FORMATS")
+        .unwrap_or_else(|_| DEFAULT_ALLOWED_FORMATS.to_string())
+        .split(',')
+        .map(|f| f.trim().to_lowercase())
+        .filter(|f| !f.is_empty())
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SniffError {
+    Unknown,
+    NotAllowed(FileFormat),
+    ExtensionMismatch { extension: String, detected: FileFormat },
+}
+
+impl std::fmt::Display for SniffError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SniffError::Unknown => write!(f, "Unrecognized file format"),
+            SniffError::NotAllowed(format) => write!(f, "File format '{}' is not allowed", format.as_str()),
+            SniffError::ExtensionMismatch { extension, detected } => write!(
+                f,
+                "File extension '.{}' does not match its content ({})",
+                extension,
+                detected.as_str()
+            ),
+        }
+    }
+}
+
+// Deteksi + cek allowlist + cek ekstensi client (jika ada) harus sesuai isi file
+pub fn detect_and_validate(head: &[u8], file_name: &str) -> Result<FileFormat, SniffError> {
+    let format = sniff(head, file_name).ok_or(SniffError::Unknown)?;
+
+    if !allowed_formats().iter().any(|f| f == format.as_str()) {
+        return Err(SniffError::NotAllowed(format));
+    }
+    if let Some(extension) = client_extension(file_name)
+        && !format.accepts_extension(&extension)
+    {
+        return Err(SniffError::ExtensionMismatch { extension, detected: format });
+    }
+    Ok(format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+
+    // ZIP kecil di memori dengan entry kosong bernama sesuai `names`
+    fn zip_with(names: &[&str]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for name in names {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(b"<x/>").unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn detects_binary_magic_bytes() {
+        assert_eq!(sniff(b"%PDF-1.7\n%\xE2\xE3", "a.pdf"), Some(FileFormat::Pdf));
+        assert_eq!(sniff(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0], "a.png"), Some(FileFormat::Png));
+        assert_eq!(sniff(&[0xFF, 0xD8, 0xFF, 0xE0, 0, 0x10], "a.jpg"), Some(FileFormat::Jpeg));
+        assert_eq!(sniff(b"GIF89a\x01\x00", "a.gif"), Some(FileFormat::Gif));
+        assert_eq!(sniff(b"RIFF\x24\x00\x00\x00WEBPVP8 ", "a.webp"), Some(FileFormat::Webp));
+        assert_eq!(sniff(&[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1, 0], "a.xls"), Some(FileFormat::Xls));
+    }
+
+    #[test]
+    fn distinguishes_xlsx_from_plain_zip() {
+        let xlsx = zip_with(&["[Content_Types].xml", "xl/workbook.xml"]);
+        assert_eq!(sniff(&xlsx, "report.xlsx"), Some(FileFormat::Xlsx));
+
+        let docx = zip_with(&["[Content_Types].xml", "word/document.xml"]);
+        assert_eq!(sniff(&docx, "report.docx"), Some(FileFormat::Zip));
+
+        let plain = zip_with(&["data/2024.csv"]);
+        assert_eq!(sniff(&plain, "bundle.zip"), Some(FileFormat::Zip));
+    }
+
+    #[test]
+    fn detects_csv_and_text() {
+        let csv = b"tanggal,keterangan,jumlah\n2024-01-01,gaji,1000\n2024-01-02,makan,-50\n";
+        assert_eq!(sniff(csv, "upload"), Some(FileFormat::Csv));
+        assert_eq!(sniff(b"a;b;c\n1;2;3\n", "upload"), Some(FileFormat::Csv));
+        assert_eq!(sniff(b"catatan bulan ini\nbelum ada transaksi\n", "upload"), Some(FileFormat::Txt));
+
+        // Ekstensi client menentukan CSV vs teks jika isinya ambigu
+        assert_eq!(sniff(b"satu baris saja", "data.csv"), Some(FileFormat::Csv));
+        assert_eq!(sniff(csv, "notes.txt"), Some(FileFormat::Txt));
+
+        // Byte kontrol biner bukan teks
+        assert_eq!(sniff(b"a,b\n\x00\x01,2\n", "data.csv"), None);
+        assert_eq!(sniff(b"", "data.csv"), None);
+    }
+
+    #[test]
+    fn short_and_truncated_input() {
+        // Lebih pendek dari magic number: tidak boleh panic / salah deteksi
+        assert_eq!(sniff(b"%PD", "a.pdf"), Some(FileFormat::Txt));
+        assert_eq!(sniff(&[0x89, b'P'], "a.png"), None);
+        assert_eq!(sniff(b"RIFF\x24\x00", "a.webp"), None);
+        assert_eq!(detect_and_validate(&[0xFF, 0xD8], "a.jpg"), Err(SniffError::Unknown));
+
+        // Karakter multi-byte yang terpotong di ujung buffer SNIFF_LEN tetap teks
+        let mut head = "nama,kota\n".repeat(SNIFF_LEN / 10 + 1).into_bytes();
+        head.truncate(SNIFF_LEN - 1);
+        head.push("é".as_bytes()[0]);
+        assert_eq!(head.len(), SNIFF_LEN);
+        assert_eq!(sniff(&head, "kota.csv"), Some(FileFormat::Csv));
+    }
+
+    #[test]
+    fn rejects_extension_mismatch() {
+        let pdf = b"%PDF-1.4\n";
+        assert_eq!(detect_and_validate(pdf, "Laporan.PDF"), Ok(FileFormat::Pdf));
+        assert_eq!(
+            detect_and_validate(pdf, "laporan.xlsx"),
+            Err(SniffError::ExtensionMismatch { extension: "xlsx".to_string(), detected: FileFormat::Pdf })
+        );
+
+        // Executable yang diganti nama jadi .png
+        assert_eq!(detect_and_validate(b"MZ\x90\x00\x03\x00", "foto.png"), Err(SniffError::Unknown));
+
+        // ZIP biasa tidak ada di allowlist default
+        let plain = zip_with(&["data.csv"]);
+        assert_eq!(detect_and_validate(&plain, "data.xlsx"), Err(SniffError::NotAllowed(FileFormat::Zip)));
+
+        // Tanpa ekstensi: cukup dari isi file
+        let xlsx = zip_with(&["[Content_Types].xml", "xl/workbook.xml"]);
+        assert_eq!(detect_and_validate(&xlsx, "upload"), Ok(FileFormat::Xlsx));
+    }
+}
@@ -0,0 +1,141 @@
+// src/core/login_guard.rs
+use axum::{
+    http::{header::RETRY_AFTER, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use bcrypt::{hash, verify, DEFAULT_COST};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use std::sync::OnceLock;
+
+use crate::db::AppState;
+use crate::models::login_attempt::LoginAttempt;
+
+// Counter direset jika tidak ada kegagalan baru dalam jendela ini
+const FAILURE_WINDOW_MINUTES: i64 = 60;
+// Mulai kegagalan ke-3, percobaan berikutnya harus menunggu 1s, 2s, 4s, ... (maks 60s)
+const DELAY_AFTER_FAILURES: i64 = 3;
+const MAX_DELAY_SECONDS: i64 = 60;
+// Lockout sementara
+const ACCOUNT_LOCK_FAILURES: i64 = 10;
+const IP_LOCK_FAILURES: i64 = 50;
+const LOCK_MINUTES: i64 = 15;
+
+pub fn account_key(email: &str) -> String {
+    format!("email:{}", email.trim().to_lowercase())
+}
+
+pub fn ip_key(ip: &str) -> String {
+    format!("ip:{}", ip)
+}
+
+fn too_many_attempts(message: &str, retry_after: i64) -> Response {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(RETRY_AFTER, retry_after.max(1).to_string())],
+        Json(json!({ "error": message, "retry_after_seconds": retry_after.max(1) })),
+    ).into_response()
+}
+
+fn is_stale(attempt: &LoginAttempt) -> bool {
+    Utc::now() - attempt.last_failure_at > Duration::minutes(FAILURE_WINDOW_MINUTES)
+}
+
+fn delay_seconds(failures: i64) -> i64 {
+    if failures < DELAY_AFTER_FAILURES {
+        return 0;
+    }
+    let exp = (failures - DELAY_AFTER_FAILURES).min(6) as u32;
+    2_i64.pow(exp).min(MAX_DELAY_SECONDS)
+}
+
+// 429 jika key sedang di-lock atau belum melewati jeda progresif.
+// Respons sama untuk email terdaftar maupun tidak, jadi tidak membocorkan keberadaan akun.
+pub async fn ensure_not_throttled(state: &AppState, keys: &[String]) -> Result<(), Response> {
+    let now = Utc::now();
+    for key in keys {
+        let attempt = match state.login_attempt_repo.find(key).await {
+            Ok(Some(a)) => a,
+            Ok(None) => continue,
+            Err(e) => {
+                eprintln!("Login Attempt Error: {}", e);
+                continue;
+            }
+        };
+
+        if let Some(locked_until) = attempt.locked_until.map(|d| d.to_chrono()) {
+            if locked_until > now {
+                return Err(too_many_attempts(
+                    "Too many failed login attempts, temporarily locked",
+                    (locked_until - now).num_seconds(),
+                ));
+            }
+        }
+        if is_stale(&attempt) {
+            continue;
+        }
+
+        let wait_until = attempt.last_failure_at + Duration::seconds(delay_seconds(attempt.failures));
+        if wait_until > now {
+            return Err(too_many_attempts(
+                "Too many failed login attempts, slow down",
+                (wait_until - now).num_seconds(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub async fn record_failure(state: &AppState, email: &str, ip: &str) {
+    for (key, lock_after) in [(account_key(email), ACCOUNT_LOCK_FAILURES), (ip_key(ip), IP_LOCK_FAILURES)] {
+        // Kegagalan lama (di luar jendela) tidak dihitung lagi
+        if let Ok(Some(existing)) = state.login_attempt_repo.find(&key).await {
+            if is_stale(&existing) {
+                let _ = state.login_attempt_repo.clear(&key).await;
+            }
+        }
+
+        match state.login_attempt_repo.record_failure(&key).await {
+            Ok(Some(attempt)) if attempt.failures >= lock_after && attempt.failures % lock_after == 0 => {
+                let until = Utc::now() + Duration::minutes(LOCK_MINUTES);
+                if let Err(e) = state.login_attempt_repo.lock(&key, until).await {
+                    eprintln!("Login Attempt Error: {}", e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Login Attempt Error: {}", e),
+        }
+    }
+}
+
+// Counter per IP sengaja tidak direset, agar login sukses ke akun sendiri
+// tidak bisa dipakai untuk menghapus jejak brute-force dari IP yang sama
+pub async fn record_success(state: &AppState, email: &str) {
+    if let Err(e) = state.login_attempt_repo.clear(&account_key(email)).await {
+        eprintln!("Login Attempt Error: {}", e);
+    }
+}
+
+// Hash dummy untuk email yang tidak terdaftar, agar waktu respons sama dengan
+// email terdaftar (bcrypt tetap dijalankan)
+fn dummy_hash() -> &'static str {
+    static DUMMY: OnceLock<String> = OnceLock::new();
+    DUMMY.get_or_init(|| hash("kepin-dummy-password", DEFAULT_COST).unwrap())
+}
+
+// Dihitung saat startup, supaya request pertama dengan email tak dikenal tidak lebih lambat
+pub fn prepare_dummy_hash() {
+    dummy_hash();
+}
+
+// Dipanggil di dalam spawn_blocking
+pub fn verify_password(password: &str, password_hash: Option<&str>) -> bool {
+    match password_hash {
+        Some(h) => verify(password, h).unwrap_or(false),
+        None => {
+            let _ = verify(password, dummy_hash());
+            false
+        }
+    }
+}
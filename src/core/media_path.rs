@@ -0,0 +1,186 @@
+// src/core/media_path.rs
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+// Root folder penyimpanan file fisik (disajikan lewat /public)
+pub const MEDIA_ROOT: &str = "media";
+pub const PUBLIC_PREFIX: &str = "/public/";
+// Folder file upload yang ditandai scanner malware: {user_id}/quarantine/..., tidak pernah disajikan
+pub const QUARANTINE_FOLDER: &str = "quarantine";
+
+#[derive(Debug, PartialEq)]
+pub enum MediaPathError {
+    Empty,
+    Absolute,
+    Traversal,
+    InvalidSegment(String),
+}
+
+impl fmt::Display for MediaPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MediaPathError::Empty => write!(f, "Empty media path"),
+            MediaPathError::Absolute => write!(f, "Absolute paths are not allowed"),
+            MediaPathError::Traversal => write!(f, "Path traversal is not allowed"),
+            MediaPathError::InvalidSegment(s) => write!(f, "Invalid path segment: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for MediaPathError {}
+
+// Satu segmen path (user_id, folder, nama file) hanya boleh berisi karakter aman
+pub fn is_safe_segment(segment: &str) -> bool {
+    !segment.is_empty()
+        && segment != "."
+        && segment != ".."
+        && segment.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+// Resolve path relatif (contoh: "{user_id}/documents/file.xlsx") ke path di dalam MEDIA_ROOT.
+// Menolak "..", path absolut, backslash, dan segmen dengan karakter aneh.
+// Bentuk tidak kanonik ("a/./b", "a//b", "a/b/") juga ditolak: pemanggil (guard /public) membaca
+// owner & folder dari string mentah, jadi string itu harus sama persis dengan key yang dibaca storage.
+pub fn resolve_relative(relative: &str) -> Result<PathBuf, MediaPathError> {
+    if relative.is_empty() {
+        return Err(MediaPathError::Empty);
+    }
+    if relative.contains('\\') || relative.contains('\0') {
+        return Err(MediaPathError::InvalidSegment(relative.to_string()));
+    }
+    if relative.starts_with('/') {
+        return Err(MediaPathError::Absolute);
+    }
+    if relative.split('/').any(|segment| segment.is_empty() || segment == ".") {
+        return Err(MediaPathError::InvalidSegment(relative.to_string()));
+    }
+
+    let mut resolved = PathBuf::from(MEDIA_ROOT);
+    for component in Path::new(relative).components() {
+        match component {
+            Component::Normal(part) => {
+                let part = part.to_str().ok_or_else(|| MediaPathError::InvalidSegment(relative.to_string()))?;
+                if !is_safe_segment(part) {
+                    return Err(MediaPathError::InvalidSegment(part.to_string()));
+                }
+                resolved.push(part);
+            }
+            Component::ParentDir => return Err(MediaPathError::Traversal),
+            Component::RootDir | Component::Prefix(_) => return Err(MediaPathError::Absolute),
+            Component::CurDir => {}
+        }
+    }
+
+    if resolved == Path::new(MEDIA_ROOT) {
+        return Err(MediaPathError::Empty);
+    }
+    Ok(resolved)
+}
+
+// Key storage dari URL publik yang tersimpan di UserUpload ("/public/{user_id}/{folder}/{file}")
+pub fn public_url_to_key(public_url: &str) -> Result<String, MediaPathError> {
+    let relative = public_url
+        .strip_prefix(PUBLIC_PREFIX)
+        .ok_or(MediaPathError::Absolute)?;
+    resolve_relative(relative)?;
+    Ok(relative.to_string())
+}
+
+pub fn public_url_for_key(key: &str) -> String {
+    format!("{}{}", PUBLIC_PREFIX, key)
+}
+
+// Folder upload milik user: media/{user_id}/{folder}
+pub fn user_dir(user_id: &str, folder: &str) -> Result<PathBuf, MediaPathError> {
+    for segment in [user_id, folder] {
+        if !is_safe_segment(segment) || segment.contains('.') {
+            return Err(MediaPathError::InvalidSegment(segment.to_string()));
+        }
+    }
+    Ok(Path::new(MEDIA_ROOT).join(user_id).join(folder))
+}
+
+// Key file milik user di storage: {user_id}/{folder}/{file_name}
+pub fn user_key(user_id: &str, folder: &str, file_name: &str) -> Result<String, MediaPathError> {
+    user_dir(user_id, folder)?;
+    if !is_safe_segment(file_name) {
+        return Err(MediaPathError::InvalidSegment(file_name.to_string()));
+    }
+    Ok(format!("{}/{}/{}", user_id, folder, file_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_safe_relative_path() {
+        assert_eq!(
+            resolve_relative("user1/pdf/abc.pdf").unwrap(),
+            Path::new(MEDIA_ROOT).join("user1").join("pdf").join("abc.pdf")
+        );
+    }
+
+    #[test]
+    fn rejects_traversal() {
+        assert_eq!(resolve_relative("../etc/passwd"), Err(MediaPathError::Traversal));
+        assert_eq!(resolve_relative("user1/../user2/a.pdf"), Err(MediaPathError::Traversal));
+        assert_eq!(resolve_relative(".."), Err(MediaPathError::Traversal));
+    }
+
+    #[test]
+    fn rejects_absolute_and_empty() {
+        assert_eq!(resolve_relative("/etc/passwd"), Err(MediaPathError::Absolute));
+        assert_eq!(resolve_relative(""), Err(MediaPathError::Empty));
+    }
+
+    #[test]
+    fn rejects_backslash_and_nul() {
+        assert!(matches!(resolve_relative("user1\\..\\a.pdf"), Err(MediaPathError::InvalidSegment(_))));
+        assert!(matches!(resolve_relative("user1/a.pdf\0.png"), Err(MediaPathError::InvalidSegment(_))));
+    }
+
+    #[test]
+    fn rejects_non_canonical_segments() {
+        for path in ["user1/./a.pdf", "./user1/a.pdf", "user1//a.pdf", "user1/a.pdf/", "."] {
+            assert!(matches!(resolve_relative(path), Err(MediaPathError::InvalidSegment(_))), "{}", path);
+        }
+    }
+
+    #[test]
+    fn rejects_unsafe_characters() {
+        assert!(matches!(resolve_relative("user1/a b.pdf"), Err(MediaPathError::InvalidSegment(_))));
+        assert!(matches!(resolve_relative("user1/%2e%2e/a.pdf"), Err(MediaPathError::InvalidSegment(_))));
+        assert!(matches!(resolve_relative("C:/a.pdf"), Err(MediaPathError::InvalidSegment(_))));
+    }
+
+    #[test]
+    fn user_dir_and_key_reject_dots_in_user_and_folder() {
+        assert!(user_dir("user1", "pdf").is_ok());
+        assert!(user_dir("user.1", "pdf").is_err());
+        assert!(user_dir("user1", "..").is_err());
+        assert!(user_dir("user1", "pdf.x").is_err());
+        assert!(user_dir("", "pdf").is_err());
+
+        assert_eq!(user_key("user1", "pdf", "abc.pdf").unwrap(), "user1/pdf/abc.pdf");
+        assert!(user_key("user1", "pdf", "..").is_err());
+        assert!(user_key("user1", "pdf", "a/b.pdf").is_err());
+        assert!(user_key("..", "pdf", "a.pdf").is_err());
+    }
+
+    #[test]
+    fn public_url_round_trip() {
+        let key = user_key("user1", "spreadsheet", "abc.xlsx").unwrap();
+        let url = public_url_for_key(&key);
+        assert_eq!(url, "/public/user1/spreadsheet/abc.xlsx");
+        assert_eq!(public_url_to_key(&url).unwrap(), key);
+    }
+
+    #[test]
+    fn public_url_to_key_rejects_bad_urls() {
+        assert_eq!(public_url_to_key("user1/pdf/a.pdf"), Err(MediaPathError::Absolute));
+        assert_eq!(public_url_to_key("/public/../a.pdf"), Err(MediaPathError::Traversal));
+        assert_eq!(public_url_to_key("/public//etc/passwd"), Err(MediaPathError::Absolute));
+        assert!(public_url_to_key("/public/user1/./pdf/a.pdf").is_err());
+    }
+}
@@ -1,2 +1,9 @@
 // src/core/mod.rs
-pub mod auth_utils;
\ No newline at end of file
+pub mod auth_utils;
+pub mod current_user;
+pub mod media_path;
+pub mod plans;
+pub mod rbac;
+pub mod login_guard;
+pub mod totp;
+pub mod file_sniff;
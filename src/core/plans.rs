@@ -0,0 +1,241 @@
+// src/core/plans.rs
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use serde_json::json;
+
+use crate::db::AppState;
+use crate::repository::usage_repo::{USAGE_ANALYSES, USAGE_UPLOADS};
+
+pub const MODE_NORMAL: &str = "normal";
+pub const MODE_FAST: &str = "fast";
+pub const MODE_DEEP: &str = "deep";
+
+const MB: u64 = 1024 * 1024;
+// Cadangan untuk boundary & header multipart di luar isi file
+const MULTIPART_OVERHEAD_BYTES: u64 = MB;
+
+// Batas ukuran upload per plan bisa diubah lewat env, contoh: MAX_UPLOAD_MB_PRO=500
+fn max_upload_bytes(env_key: &str, default_mb: u64) -> u64 {
+    std::env::var(env_key)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(default_mb)
+        * MB
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Plan {
+    Basic,
+    Pro,
+    Enterprise,
+}
+
+// Batasan per plan. None = tanpa batas.
+pub struct PlanLimits {
+    pub monthly_uploads: Option<i64>,
+    pub monthly_analyses: Option<i64>,
+    pub max_file_size_bytes: u64,
+    pub analyze_modes: &'static [&'static str],
+}
+
+impl Plan {
+    // Plan yang tidak dikenal diperlakukan sebagai basic
+    pub fn parse(plan: &str) -> Self {
+        match plan.to_lowercase().as_str() {
+            "pro" => Plan::Pro,
+            "enterprise" => Plan::Enterprise,
+            _ => Plan::Basic,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Plan::Basic => "basic",
+            Plan::Pro => "pro",
+            Plan::Enterprise => "enterprise",
+        }
+    }
+
+    pub fn limits(&self) -> PlanLimits {
+        match self {
+            Plan::Basic => PlanLimits {
+                monthly_uploads: Some(20),
+                monthly_analyses: Some(20),
+                max_file_size_bytes: max_upload_bytes("MAX_UPLOAD_MB_BASIC", 10),
+                analyze_modes: &[MODE_NORMAL, MODE_FAST],
+            },
+            Plan::Pro => PlanLimits {
+                monthly_uploads: Some(500),
+                monthly_analyses: Some(500),
+                max_file_size_bytes: max_upload_bytes("MAX_UPLOAD_MB_PRO", 200),
+                analyze_modes: &[MODE_NORMAL, MODE_FAST, MODE_DEEP],
+            },
+            Plan::Enterprise => PlanLimits {
+                monthly_uploads: None,
+                monthly_analyses: None,
+                max_file_size_bytes: max_upload_bytes("MAX_UPLOAD_MB_ENTERPRISE", 1024),
+                analyze_modes: &[MODE_NORMAL, MODE_FAST, MODE_DEEP],
+            },
+        }
+    }
+}
+
+// Batas body request untuk route upload (DefaultBodyLimit): plan terbesar + overhead multipart.
+// Batas per plan tetap dicek saat streaming.
+pub fn max_request_body_bytes() -> usize {
+    [Plan::Basic, Plan::Pro, Plan::Enterprise]
+        .iter()
+        .map(|p| p.limits().max_file_size_bytes)
+        .max()
+        .unwrap_or(0)
+        .saturating_add(MULTIPART_OVERHEAD_BYTES) as usize
+}
+
+// Batas body POST /upload/batch (semua file sekaligus), env MAX_BATCH_UPLOAD_MB. Batas per file tetap per plan.
+pub fn max_batch_body_bytes() -> usize {
+    max_upload_bytes("MAX_BATCH_UPLOAD_MB", 2048).saturating_add(MULTIPART_OVERHEAD_BYTES) as usize
+}
+
+// Content-Length jelas melebihi batas plan: tolak sebelum membaca body
+pub fn ensure_request_size(plan: Plan, content_length: Option<u64>) -> Result<(), Response> {
+    match content_length {
+        Some(len) if len > plan.limits().max_file_size_bytes + MULTIPART_OVERHEAD_BYTES => ensure_file_size(plan, len),
+        _ => Ok(()),
+    }
+}
+
+// Periode kuota bulanan, contoh: "2025-12"
+pub fn current_period() -> String {
+    Utc::now().format("%Y-%m").to_string()
+}
+
+fn quota_error(status: StatusCode, message: String, plan: Plan) -> Response {
+    (status, Json(json!({
+        "status": "error",
+        "message": message,
+        "plan": plan.as_str(),
+    }))).into_response()
+}
+
+fn db_error(e: mongodb::error::Error) -> Response {
+    eprintln!("Usage Error: {}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "Database Error" }))).into_response()
+}
+
+// 429 jika kuota upload bulan ini sudah habis. Hanya cek awal agar file tidak di-stream sia-sia;
+// kuota baru benar-benar dipakai (atomik) di consume_upload_quota.
+pub async fn ensure_upload_quota(state: &AppState, user_id: &str, plan: Plan) -> Result<(), Response> {
+    let Some(limit) = plan.limits().monthly_uploads else { return Ok(()) };
+    let usage = state.usage_repo.get(user_id, &current_period()).await.map_err(db_error)?;
+
+    if usage.uploads >= limit {
+        return Err(quota_error(
+            StatusCode::TOO_MANY_REQUESTS,
+            format!("Monthly upload quota reached ({}/{})", usage.uploads, limit),
+            plan,
+        ));
+    }
+    Ok(())
+}
+
+// Pakai satu kuota upload sebelum upload dicatat. Counter hanya naik jika masih di bawah limit,
+// sehingga request paralel yang sama-sama lolos ensure_upload_quota tidak bisa melewati kuota.
+pub async fn consume_upload_quota(state: &AppState, user_id: &str, plan: Plan) -> Result<(), Response> {
+    let period = current_period();
+    let Some(limit) = plan.limits().monthly_uploads else {
+        // Tanpa batas: counter hanya untuk statistik, gagal dicatat tidak menggagalkan upload
+        if let Err(e) = state.usage_repo.increment(user_id, &period, USAGE_UPLOADS).await {
+            eprintln!("Usage Error: {}", e);
+        }
+        return Ok(());
+    };
+
+    match state.usage_repo.increment_within(user_id, &period, USAGE_UPLOADS, limit).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(quota_error(
+            StatusCode::TOO_MANY_REQUESTS,
+            format!("Monthly upload quota reached ({} per month)", limit),
+            plan,
+        )),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+// Kembalikan kuota yang sudah dipakai jika file akhirnya tidak jadi dicatat
+pub async fn release_upload_quota(state: &AppState, user_id: &str) {
+    if let Err(e) = state.usage_repo.decrement(user_id, &current_period(), USAGE_UPLOADS).await {
+        eprintln!("Usage Error: {}", e);
+    }
+}
+
+// 413 jika ukuran file melebihi batas plan
+pub fn ensure_file_size(plan: Plan, size_bytes: u64) -> Result<(), Response> {
+    let max = plan.limits().max_file_size_bytes;
+    if size_bytes > max {
+        return Err(quota_error(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("File too large for plan ({} MB max)", max / MB),
+            plan,
+        ));
+    }
+    Ok(())
+}
+
+// 402 jika mode analisa tidak termasuk plan, 429 jika kuota analisa habis
+pub async fn ensure_analyze_allowed(state: &AppState, user_id: &str, plan: Plan, mode: &str) -> Result<(), Response> {
+    let limits = plan.limits();
+    if !limits.analyze_modes.contains(&mode) {
+        return Err(quota_error(
+            StatusCode::PAYMENT_REQUIRED,
+            format!("Analyze mode '{}' is not available on the {} plan", mode, plan.as_str()),
+            plan,
+        ));
+    }
+
+    let Some(limit) = limits.monthly_analyses else { return Ok(()) };
+    let usage = state.usage_repo.get(user_id, &current_period()).await.map_err(db_error)?;
+
+    if usage.analyses >= limit {
+        return Err(quota_error(
+            StatusCode::TOO_MANY_REQUESTS,
+            format!("Monthly analysis quota reached ({}/{})", usage.analyses, limit),
+            plan,
+        ));
+    }
+    Ok(())
+}
+
+pub async fn record_analysis(state: &AppState, user_id: &str) {
+    if let Err(e) = state.usage_repo.increment(user_id, &current_period(), USAGE_ANALYSES).await {
+        eprintln!("Usage Error: {}", e);
+    }
+}
+
+// Ringkasan kuota untuk /auth/me
+pub async fn quota_summary(state: &AppState, user_id: &str, plan: Plan) -> serde_json::Value {
+    let period = current_period();
+    let usage = state.usage_repo.get(user_id, &period).await.unwrap_or_default();
+    let limits = plan.limits();
+
+    let remaining = |limit: Option<i64>, used: i64| limit.map(|l| (l - used).max(0));
+
+    json!({
+        "period": period,
+        "uploads": {
+            "used": usage.uploads,
+            "limit": limits.monthly_uploads,
+            "remaining": remaining(limits.monthly_uploads, usage.uploads),
+        },
+        "analyses": {
+            "used": usage.analyses,
+            "limit": limits.monthly_analyses,
+            "remaining": remaining(limits.monthly_analyses, usage.analyses),
+        },
+        "max_file_size_bytes": limits.max_file_size_bytes,
+        "analyze_modes": limits.analyze_modes,
+    })
+}
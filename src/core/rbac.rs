@@ -0,0 +1,72 @@
+// src/core/rbac.rs
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+
+use crate::core::current_user::CurrentUser;
+
+pub const ROLE_ADMIN: &str = "admin";
+pub const ROLE_ANALYST: &str = "analyst";
+pub const ROLE_VIEWER: &str = "viewer";
+pub const USER_ROLES: [&str; 3] = [ROLE_ADMIN, ROLE_ANALYST, ROLE_VIEWER];
+
+// Role global user. Urutan menentukan hak akses: viewer < analyst < admin
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Role {
+    Viewer,
+    Analyst,
+    Admin,
+}
+
+impl Role {
+    // Role yang tidak dikenal diperlakukan sebagai viewer (paling terbatas)
+    pub fn parse(role: &str) -> Self {
+        match role {
+            ROLE_ADMIN => Role::Admin,
+            ROLE_ANALYST => Role::Analyst,
+            _ => Role::Viewer,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => ROLE_ADMIN,
+            Role::Analyst => ROLE_ANALYST,
+            Role::Viewer => ROLE_VIEWER,
+        }
+    }
+}
+
+fn check_role(current: &CurrentUser, required: Role) -> Result<(), Response> {
+    let role = Role::parse(&current.user.role);
+    if role >= required {
+        return Ok(());
+    }
+    Err((StatusCode::FORBIDDEN, Json(json!({
+        "error": format!("Requires role '{}' (current: '{}')", required.as_str(), role.as_str())
+    }))).into_response())
+}
+
+// Middleware untuk route upload/hapus/analyze
+pub async fn require_analyst(current: CurrentUser, req: Request, next: Next) -> Response {
+    if let Err(resp) = check_role(&current, Role::Analyst) {
+        return resp;
+    }
+    next.run(req).await
+}
+
+// Middleware untuk route /admin: hanya sesi browser, API key milik admin pun ditolak
+pub async fn require_admin(current: CurrentUser, req: Request, next: Next) -> Response {
+    if let Err(resp) = current.require_session() {
+        return resp;
+    }
+    if let Err(resp) = check_role(&current, Role::Admin) {
+        return resp;
+    }
+    next.run(req).await
+}
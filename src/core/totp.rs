@@ -0,0 +1,195 @@
+// src/core/totp.rs
+// TOTP (RFC 6238) di atas HOTP (RFC 4226) dengan HMAC-SHA1, 6 digit, periode 30 detik.
+// Semua fungsi murni (waktu dikirim sebagai parameter) sehingga bisa dicek terhadap
+// test vector RFC 6238 Appendix B, contoh: secret ASCII "12345678901234567890",
+// T = 59 -> 94287082 (8 digit), T = 1111111109 -> 07081804.
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::{Rng, RngCore};
+use sha1::Sha1;
+
+pub const TOTP_DIGITS: u32 = 6;
+pub const TOTP_STEP_SECONDS: i64 = 30;
+// Toleransi selisih jam client: 1 periode sebelum/sesudah
+pub const TOTP_SKEW_STEPS: i64 = 1;
+pub const RECOVERY_CODE_COUNT: usize = 10;
+pub const ISSUER: &str = "Kepin";
+
+// Secret 160-bit, di-encode base32 (format yang dipakai aplikasi authenticator)
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    BASE32_NOPAD.encode(&bytes)
+}
+
+pub fn decode_secret(secret: &str) -> Option<Vec<u8>> {
+    let normalized: String = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '=')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    BASE32_NOPAD.decode(normalized.as_bytes()).ok()
+}
+
+// RFC 4226 section 5.3 (dynamic truncation)
+pub fn hotp(key: &[u8], counter: u64, digits: u32) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = ((hash[offset] as u32 & 0x7f) << 24)
+        | ((hash[offset + 1] as u32) << 16)
+        | ((hash[offset + 2] as u32) << 8)
+        | (hash[offset + 3] as u32);
+
+    let code = binary % 10u32.pow(digits);
+    format!("{:0width$}", code, width = digits as usize)
+}
+
+pub fn time_step(unix_time: i64) -> i64 {
+    unix_time.div_euclid(TOTP_STEP_SECONDS)
+}
+
+pub fn totp_at(key: &[u8], unix_time: i64, digits: u32) -> String {
+    hotp(key, time_step(unix_time) as u64, digits)
+}
+
+// Mengembalikan time step yang cocok (untuk mencegah replay kode yang sama)
+pub fn verify_code(secret: &str, code: &str, unix_time: i64) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let key = decode_secret(secret)?;
+    let current = time_step(unix_time);
+
+    (current - TOTP_SKEW_STEPS..=current + TOTP_SKEW_STEPS)
+        .filter(|step| *step >= 0)
+        .find(|step| constant_time_eq(totp_at(&key, step * TOTP_STEP_SECONDS, TOTP_DIGITS).as_bytes(), code.as_bytes()))
+}
+
+// Anti-replay: kode hanya berlaku untuk time step yang lebih baru dari yang terakhir dipakai
+pub fn is_unused_step(last_step: Option<i64>, step: i64) -> bool {
+    last_step.is_none_or(|last| step > last)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn url_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+// URI untuk QR code, contoh: otpauth://totp/Kepin:user%40mail.com?secret=...&issuer=Kepin
+pub fn otpauth_uri(account: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        url_encode(ISSUER),
+        url_encode(account),
+        secret,
+        url_encode(ISSUER),
+        TOTP_DIGITS,
+        TOTP_STEP_SECONDS
+    )
+}
+
+// Kode pemulihan sekali pakai, format "xxxxx-xxxxx" (tanpa huruf/angka yang mirip)
+pub fn generate_recovery_codes() -> Vec<String> {
+    const ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+    let mut rng = rand::thread_rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let raw: String = (0..10).map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())] as char).collect();
+            format!("{}-{}", &raw[..5], &raw[5..])
+        })
+        .collect()
+}
+
+// Kode pemulihan dibandingkan tanpa memedulikan huruf besar/kecil dan tanda "-"
+pub fn normalize_recovery_code(code: &str) -> String {
+    let cleaned: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    if cleaned.len() == 10 {
+        format!("{}-{}", &cleaned[..5], &cleaned[5..])
+    } else {
+        cleaned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Secret ASCII "12345678901234567890" dari RFC 4226 / RFC 6238 (SHA1)
+    const RFC_KEY: &[u8] = b"12345678901234567890";
+
+    fn rfc_secret() -> String {
+        BASE32_NOPAD.encode(RFC_KEY)
+    }
+
+    #[test]
+    fn hotp_matches_rfc4226_vectors() {
+        let expected = [
+            "755224", "287082", "359152", "969429", "338314",
+            "254676", "287922", "162583", "399871", "520489",
+        ];
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(hotp(RFC_KEY, counter as u64, 6), *code, "counter {}", counter);
+        }
+    }
+
+    #[test]
+    fn totp_matches_rfc6238_sha1_vectors() {
+        assert_eq!(totp_at(RFC_KEY, 59, 8), "94287082");
+        assert_eq!(totp_at(RFC_KEY, 1111111109, 8), "07081804");
+        assert_eq!(totp_at(RFC_KEY, 20000000000, 8), "65353130");
+    }
+
+    #[test]
+    fn verify_code_accepts_within_skew_window() {
+        let secret = rfc_secret();
+        let code = totp_at(RFC_KEY, 59, TOTP_DIGITS); // time step 1
+
+        assert_eq!(verify_code(&secret, &code, 59), Some(1));
+        assert_eq!(verify_code(&secret, &code, 30 + 59), Some(1));
+        assert_eq!(verify_code(&secret, &code, 0), Some(1));
+        assert_eq!(verify_code(&secret, &format!(" {} ", code), 59), Some(1));
+    }
+
+    #[test]
+    fn verify_code_rejects_outside_skew_window_and_bad_input() {
+        let secret = rfc_secret();
+        let code = totp_at(RFC_KEY, 59, TOTP_DIGITS);
+
+        assert_eq!(verify_code(&secret, &code, 59 + 2 * TOTP_STEP_SECONDS), None);
+        assert_eq!(verify_code(&secret, "000000", 59), None);
+        assert_eq!(verify_code(&secret, "12345", 59), None);
+        assert_eq!(verify_code(&secret, "12a456", 59), None);
+        assert_eq!(verify_code("not base32!", &code, 59), None);
+    }
+
+    #[test]
+    fn replayed_step_is_rejected() {
+        let secret = rfc_secret();
+        let code = totp_at(RFC_KEY, 59, TOTP_DIGITS);
+        let step = verify_code(&secret, &code, 59).unwrap();
+
+        assert!(is_unused_step(None, step));
+        // Kode yang sama di periode berikutnya menghasilkan step yang sama -> replay
+        let replay = verify_code(&secret, &code, 59 + TOTP_STEP_SECONDS).unwrap();
+        assert!(!is_unused_step(Some(step), replay));
+        assert!(!is_unused_step(Some(step + 1), step));
+        assert!(is_unused_step(Some(step), step + 1));
+    }
+}
@@ -2,19 +2,80 @@ use mongodb::{Client, Database};
 use crate::repository::user_repo::UserRepository;
 use crate::repository::upload_repo::UploadRepository;
 use crate::repository::financial_repo::FinancialRepository; // Import baru
+use crate::repository::session_repo::SessionRepository;
+use crate::repository::auth_token_repo::AuthTokenRepository;
+use crate::repository::api_key_repo::ApiKeyRepository;
+use crate::repository::usage_repo::UsageRepository;
+use crate::repository::workspace_repo::WorkspaceRepository;
+use crate::repository::login_attempt_repo::LoginAttemptRepository;
+use crate::repository::external_identity_repo::ExternalIdentityRepository;
+use crate::repository::data_export_repo::DataExportRepository;
+use crate::repository::media_blob_repo::MediaBlobRepository;
 use crate::services::extractor_client::GrpcClient;
+use crate::services::mailer::Mailer;
+use crate::services::scanner::Scanner;
+use crate::services::oidc::OidcClient;
+use crate::services::storage::Storage;
+use std::sync::Arc;
 
 pub struct AppState {
     pub db: mongodb::Database,
     pub user_repo: crate::repository::user_repo::UserRepository,
     pub upload_repo: crate::repository::upload_repo::UploadRepository,
     pub financial_repo: FinancialRepository, // Tambah field ini
+    pub session_repo: SessionRepository,
+    pub auth_token_repo: AuthTokenRepository,
+    pub api_key_repo: ApiKeyRepository,
+    pub usage_repo: UsageRepository,
+    pub workspace_repo: WorkspaceRepository,
+    pub login_attempt_repo: LoginAttemptRepository,
+    pub external_identity_repo: ExternalIdentityRepository,
+    pub data_export_repo: DataExportRepository,
+    pub media_blob_repo: MediaBlobRepository,
     pub kolosal_key: String,
+    pub jwt_secret: String,
+    pub app_base_url: String, // URL frontend untuk link di email
+    pub mailer: Arc<dyn Mailer>,
+    pub storage: Arc<dyn Storage>, // file media: disk lokal atau S3-compatible
+    pub scanner: Arc<dyn Scanner>, // pemindaian malware file upload (clamd / no-op)
+    pub oidc: Option<OidcClient>, // None jika login OIDC tidak dikonfigurasi
     pub grpc_client: GrpcClient,
+    pub mongo_client: Client,
+    pub supports_transactions: bool, // replica set / mongos, lihat supports_transactions()
 }
 
-pub async fn init_db() -> Database {
+// Client dibutuhkan untuk session transaksi, Database untuk repository
+pub async fn init_db() -> (Client, Database) {
     let uri = std::env::var("MONGODB_URI").expect("MONGODB_URI error");
     let client = Client::with_uri_str(uri).await.unwrap();
-    client.database("kepin")
-}
\ No newline at end of file
+    let database = client.database("kepin");
+    (client, database)
+}
+
+// Unique index yang dibutuhkan upsert & pengecekan duplikat yang atomik.
+// Gagal dibuat (mis. data lama sudah duplikat) hanya dicatat, server tetap jalan.
+pub async fn ensure_indexes(state: &AppState) {
+    let results = [
+        ("users", state.user_repo.ensure_indexes().await),
+        ("sessions", state.session_repo.ensure_indexes().await),
+        ("usage_counters", state.usage_repo.ensure_indexes().await),
+        ("media_blobs", state.media_blob_repo.ensure_indexes().await),
+    ];
+    for (collection, result) in results {
+        if let Err(e) = result {
+            eprintln!("Index Error ({}): {}", collection, e);
+        }
+    }
+}
+
+// Transaksi multi-dokumen hanya tersedia di replica set atau sharded cluster (mongos),
+// tidak di server standalone
+pub async fn supports_transactions(db: &Database) -> bool {
+    match db.run_command(mongodb::bson::doc! { "hello": 1 }, None).await {
+        Ok(reply) => reply.contains_key("setName") || reply.get_str("msg").is_ok_and(|msg| msg == "isdbgrid"),
+        Err(e) => {
+            eprintln!("MongoDB hello Error: {}", e);
+            false
+        }
+    }
+}
@@ -6,24 +6,52 @@ mod repository;
 mod services;
 
 use axum::{
-    routing::{post, get, delete},
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{post, get, delete, patch},
     Router,
-    http::{header::{CONTENT_TYPE, AUTHORIZATION, COOKIE}, Method, HeaderValue},
+    http::{header::{CONTENT_TYPE, AUTHORIZATION, COOKIE}, Method, HeaderName, HeaderValue},
 };
 use std::{sync::Arc, env, net::SocketAddr};
 
+use crate::core::current_user::WORKSPACE_HEADER;
+use crate::core::plans;
+use crate::core::rbac::ROLE_ADMIN;
 use crate::db::AppState;
-use crate::repository::{user_repo::UserRepository, upload_repo::UploadRepository, financial_repo::FinancialRepository}; 
+use crate::repository::{user_repo::UserRepository, upload_repo::UploadRepository, financial_repo::FinancialRepository, session_repo::SessionRepository, auth_token_repo::AuthTokenRepository, api_key_repo::ApiKeyRepository, usage_repo::UsageRepository, workspace_repo::WorkspaceRepository, login_attempt_repo::LoginAttemptRepository, external_identity_repo::ExternalIdentityRepository, data_export_repo::DataExportRepository, media_blob_repo::MediaBlobRepository}; 
 use crate::services::extractor_client::GrpcClient;
+use crate::services::mailer::mailer_from_env;
+use crate::services::scanner::scanner_from_env;
+use crate::services::oidc::{OidcClient, OidcConfig};
+use crate::services::storage::storage_from_env;
 use tower_cookies::CookieManagerLayer;
 use tower_http::cors::CorsLayer;
-use tower_http::services::ServeDir;
 
 #[tokio::main]
 async fn main() {
     dotenvy::dotenv().ok();
     
-    let database = db::init_db().await;
+    let (mongo_client, database) = db::init_db().await;
+
+    // Command maintenance: `kepinAPI reconcile [--fix [--delete-missing-uploads]]`, tidak menjalankan server
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("reconcile") {
+        let fix = args.iter().any(|a| a == "--fix");
+        let delete_missing = fix && args.iter().any(|a| a == "--delete-missing-uploads");
+        match services::reconcile::run(&database, storage_from_env().as_ref(), fix, delete_missing).await {
+            Ok(report) => report.print(fix, delete_missing),
+            Err(e) => {
+                eprintln!("Reconcile Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let supports_transactions = db::supports_transactions(&database).await;
+    if !supports_transactions {
+        println!("⚠️ MongoDB standalone: cascade delete berjalan tanpa transaksi");
+    }
 
     // Inisialisasi gRPC Client
     let grpc_addr = env::var("GRPC_EXTRACTOR_URL").unwrap_or_else(|_| "http://127.0.0.1:50051".to_string());
@@ -36,37 +64,118 @@ async fn main() {
         user_repo: UserRepository::new(&database),
         upload_repo: UploadRepository::new(&database),
         financial_repo: FinancialRepository::new(&database),
+        session_repo: SessionRepository::new(&database),
+        auth_token_repo: AuthTokenRepository::new(&database),
+        api_key_repo: ApiKeyRepository::new(&database),
+        usage_repo: UsageRepository::new(&database),
+        workspace_repo: WorkspaceRepository::new(&database),
+        login_attempt_repo: LoginAttemptRepository::new(&database),
+        external_identity_repo: ExternalIdentityRepository::new(&database),
+        data_export_repo: DataExportRepository::new(&database),
+        media_blob_repo: MediaBlobRepository::new(&database),
         kolosal_key: env::var("KOLOSAL_API_KEY").unwrap_or_else(|_| "default".to_string()),
+        jwt_secret: env::var("JWT_SECRET").expect("JWT_SECRET must be set"),
+        app_base_url: env::var("APP_BASE_URL").unwrap_or_else(|_| "http://localhost:3000".to_string()),
+        mailer: mailer_from_env(),
+        storage: storage_from_env(),
+        scanner: scanner_from_env(),
+        oidc: OidcConfig::from_env().map(OidcClient::new),
         grpc_client,
+        mongo_client,
+        supports_transactions,
     });
 
+    db::ensure_indexes(&state).await;
+
+    tokio::task::spawn_blocking(core::login_guard::prepare_dummy_hash);
+
+    // Purge berkala upload di trash yang melewati masa retensi
+    tokio::spawn(services::trash::run_purge_job(state.clone()));
+
+    // Bootstrap admin: email di ADMIN_EMAILS (dipisah koma) otomatis menjadi admin
+    let admin_emails: Vec<String> = env::var("ADMIN_EMAILS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|e| e.trim().to_string())
+        .filter(|e| !e.is_empty())
+        .collect();
+    if let Err(e) = state.user_repo.promote_admins(&admin_emails, ROLE_ADMIN).await {
+        eprintln!("Admin bootstrap error: {}", e);
+    }
+
     let cors = CorsLayer::new()
         .allow_origin("http://localhost:3000".parse::<HeaderValue>().unwrap())
         .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
-        .allow_headers([CONTENT_TYPE, AUTHORIZATION, COOKIE])
+        .allow_headers([CONTENT_TYPE, AUTHORIZATION, COOKIE, HeaderName::from_static(WORKSPACE_HEADER)])
         .allow_credentials(true);
 
+    // Menyajikan file media dari storage (lokal / S3), hanya untuk pemilik file
+    let public_media = Router::new()
+        .fallback(api::media::serve_public_media)
+        .layer(middleware::from_fn_with_state(state.clone(), api::media::guard_public_media));
+
     let app = Router::new()
-        // Menyajikan file statis dari folder media
-        .nest_service("/public", ServeDir::new("media"))
+        .nest("/public", public_media)
         .nest("/api/v1", Router::new()
             // Auth Routes
             .nest("/auth", Router::new()
                 .route("/register", post(api::auth::register))
                 .route("/login", post(api::auth::login))
+                .route("/login/2fa", post(api::auth::login_mfa))
+                .route("/oidc/login", get(api::oidc::oidc_login))
+                .route("/oidc/callback", get(api::oidc::oidc_callback))
                 .route("/logout", post(api::auth::logout))
-                .route("/me", get(api::auth::me))
+                .route("/refresh", post(api::auth::refresh))
+                .route("/verify-email", post(api::auth::verify_email))
+                .route("/forgot-password", post(api::auth::forgot_password))
+                .route("/reset-password", post(api::auth::reset_password))
+                .route("/me", get(api::auth::me).patch(api::profile::update_me).delete(api::profile::delete_me))
+                .route("/me/avatar", post(api::profile::upload_avatar))
+                .route("/change-password", post(api::profile::change_password))
+                .route("/export", get(api::data_export::list_exports).post(api::data_export::request_export))
+                .route("/export/:id", get(api::data_export::get_export))
+                .route("/export/:id/download", get(api::data_export::download_export))
+                .route("/2fa/setup", post(api::two_factor::setup_totp))
+                .route("/2fa/enable", post(api::two_factor::enable_totp))
+                .route("/2fa/disable", post(api::two_factor::disable_totp))
+                .route("/2fa/recovery-codes", post(api::two_factor::regenerate_recovery_codes))
+                .route("/sessions", get(api::sessions::list_sessions).delete(api::sessions::revoke_all_sessions))
+                .route("/sessions/:id", delete(api::sessions::revoke_session))
             )
-            // Upload Routes
-            .route("/upload", post(api::uploads::upload_file))
-            .route("/upload/:id", delete(api::uploads::delete_file))
+            // API Key Routes (akses machine-to-machine via Authorization: Bearer)
+            .route("/api-keys", get(api::api_keys::list_api_keys).post(api::api_keys::create_api_key))
+            .route("/api-keys/:id", delete(api::api_keys::revoke_api_key))
+
+            // Workspace Routes (pilih workspace aktif via header X-Workspace-Id)
+            .route("/workspaces", get(api::workspaces::list_workspaces).post(api::workspaces::create_workspace))
+            .route("/workspaces/:id/members", get(api::workspaces::list_members).post(api::workspaces::invite_member))
+            .route("/workspaces/:id/members/:user_id", patch(api::workspaces::update_member).delete(api::workspaces::remove_member))
+
+            // Upload Routes (viewer hanya boleh membaca)
             .route("/uploads", get(api::uploads::get_my_uploads))
-            
-            // Analyze & Financial Data Routes
-            .route("/normal_analyze", post(api::normal_analyze::normal_analyze_document_stream))
-            .route("/fast_analyze", post(api::fast_analyze::fast_analyze_document_stream))
-            .route("/deep_analyze", post(api::deep_analyze::deep_analyze_document_stream))
-            
+            .route("/uploads/trash", get(api::uploads::list_trash))
+            .route("/upload/:id/url", get(api::uploads::get_download_url))
+
+            // Upload & Analyze Routes: minimal role analyst
+            .merge(Router::new()
+                .route("/upload", post(api::uploads::upload_file).layer(DefaultBodyLimit::max(plans::max_request_body_bytes())))
+                .route("/upload/batch", post(api::uploads::upload_batch).layer(DefaultBodyLimit::max(plans::max_batch_body_bytes())))
+                .route("/upload/:id", delete(api::uploads::delete_file).patch(api::uploads::update_upload))
+                .route("/upload/:id/restore", post(api::uploads::restore_upload))
+                .route("/uploads/trash/:id", delete(api::uploads::purge_trashed_upload))
+                .route("/normal_analyze", post(api::normal_analyze::normal_analyze_document_stream))
+                .route("/fast_analyze", post(api::fast_analyze::fast_analyze_document_stream))
+                .route("/deep_analyze", post(api::deep_analyze::deep_analyze_document_stream))
+                .route_layer(middleware::from_fn_with_state(state.clone(), core::rbac::require_analyst))
+            )
+
+            // Admin Routes
+            .nest("/admin", Router::new()
+                .route("/users", get(api::admin::list_users))
+                .route("/users/:id", patch(api::admin::update_user))
+                .route_layer(middleware::from_fn_with_state(state.clone(), core::rbac::require_admin))
+            )
+
             // Endpoint untuk data finansial dan statistik dashboard
             .route("/financial-data", get(api::normal_analyze::get_financial_data))
             .route("/financial/stats", get(api::normal_analyze::get_financial_stats))
@@ -80,5 +189,6 @@ async fn main() {
     
     println!("🚀 Server running on http://{}", addr);
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // ConnectInfo dibutuhkan untuk mencatat IP pada sesi login
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
 }
\ No newline at end of file
@@ -0,0 +1,35 @@
+// src/models/api_key.rs
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+
+// Scope yang bisa diberikan ke API key
+pub const SCOPE_READ: &str = "read";       // GET uploads, financial-data, stats
+pub const SCOPE_UPLOAD: &str = "upload";   // POST/DELETE upload
+pub const SCOPE_ANALYZE: &str = "analyze"; // normal/fast/deep analyze
+pub const ALL_SCOPES: [&str; 3] = [SCOPE_READ, SCOPE_UPLOAD, SCOPE_ANALYZE];
+
+// API key untuk akses machine-to-machine. Key asli hanya ditampilkan sekali, yang disimpan hanya hash-nya.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiKey {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+
+    pub user_id: String,
+    pub name: String,
+    pub prefix: String, // beberapa karakter awal key, untuk identifikasi di dashboard
+    pub key_hash: String,
+    pub scopes: Vec<String>,
+    pub revoked: bool,
+
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub last_used_at: Option<mongodb::bson::DateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scopes: Option<Vec<String>>,
+}
@@ -0,0 +1,26 @@
+// src/models/auth_token.rs
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+
+pub const PURPOSE_VERIFY_EMAIL: &str = "verify_email";
+pub const PURPOSE_RESET_PASSWORD: &str = "reset_password";
+// Tiket langkah kedua login (setelah password benar, sebelum kode 2FA)
+pub const PURPOSE_MFA_LOGIN: &str = "mfa_login";
+
+// Token sekali pakai (verifikasi email / reset password / login 2FA). Hanya hash yang disimpan.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuthToken {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+
+    pub user_id: String,
+    pub purpose: String,
+    pub token_hash: String,
+    pub used: bool,
+
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expires_at: DateTime<Utc>,
+}
@@ -0,0 +1,31 @@
+// src/models/data_export.rs
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+
+pub const EXPORT_PENDING: &str = "pending";
+pub const EXPORT_READY: &str = "ready";
+pub const EXPORT_FAILED: &str = "failed";
+
+// Job export data akun (ZIP). File hasil disimpan di luar folder media (tidak bisa diakses via /public).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DataExport {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+
+    pub user_id: String,
+    pub status: String, // pending | ready | failed
+    #[serde(default)]
+    pub file_path: Option<String>,
+    #[serde(default)]
+    pub size_bytes: Option<i64>,
+    #[serde(default)]
+    pub error: Option<String>,
+
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub completed_at: Option<mongodb::bson::DateTime>,
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expires_at: DateTime<Utc>,
+}
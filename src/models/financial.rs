@@ -30,6 +30,8 @@ pub struct FinancialRecord {
     
     pub user_id: String,       // ID User
     pub id_userupload: String, 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>, // ikut workspace dari upload-nya
     pub source_file: String,   // Path file
     
     #[serde(flatten)]          // Data AI digabung ke root dokumen
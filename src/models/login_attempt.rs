@@ -0,0 +1,20 @@
+// src/models/login_attempt.rs
+use serde::{Deserialize, Serialize};
+use mongodb::bson::{self, oid::ObjectId};
+use chrono::{DateTime, Utc};
+
+// Counter login gagal per akun ("email:<email>") atau per IP ("ip:<ip>")
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LoginAttempt {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+
+    pub key: String,
+    #[serde(default)]
+    pub failures: i64,
+
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub last_failure_at: DateTime<Utc>,
+    #[serde(default)]
+    pub locked_until: Option<bson::DateTime>,
+}
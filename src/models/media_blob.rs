@@ -0,0 +1,22 @@
+// src/models/media_blob.rs
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+
+// File fisik content-addressed: media/{user_id}/{file_type}/{sha256}.{ext}.
+// Beberapa upload dengan isi identik memakai file yang sama; file dihapus saat ref_count 0.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MediaBlob {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+
+    pub file_path: String, // URL publik, unik per file fisik
+    pub user_id: String,   // pemilik folder media
+    pub sha256: String,
+    pub size_bytes: i64,
+    #[serde(default)]
+    pub ref_count: i64,
+
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
@@ -1,4 +1,13 @@
 // src/models/mod.rs
 pub mod user;
 pub mod upload;
-pub mod financial; 
\ No newline at end of file
+pub mod financial; 
+pub mod session;
+pub mod auth_token;
+pub mod api_key;
+pub mod usage;
+pub mod workspace;
+pub mod login_attempt;
+pub mod external_identity;
+pub mod data_export;
+pub mod media_blob;
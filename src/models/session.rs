@@ -0,0 +1,24 @@
+// src/models/session.rs
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+
+// Satu sesi login (satu device/browser). Refresh token hanya disimpan dalam bentuk hash.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Session {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+
+    pub user_id: String,
+    pub refresh_token_hash: String,
+    pub user_agent: String,
+    pub ip: String,
+    pub revoked: bool,
+
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub last_used_at: DateTime<Utc>,
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expires_at: DateTime<Utc>,
+}
@@ -1,6 +1,7 @@
 // src/models/upload.rs
-use serde::{Deserialize, Serialize};
-use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Deserializer, Serialize};
+use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chrono::{DateTime, Utc};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -11,9 +12,171 @@ pub struct UserUpload {
     pub user_id: String,      // idUser
     pub file_name: String,    // file (nama file asli/aman)
     pub file_path: String,    // file (path lengkap atau url)
-    pub file_type: String,    // jenis file (images, documents, others)
-    
+    pub file_type: String,    // jenis file dari isi file: pdf, spreadsheet, image, text (upload lama: images, documents, others)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>, // format hasil deteksi: pdf, xlsx, xls, csv, png, ...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>, // hash isi file; upload lama tidak punya
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_bytes: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_name: Option<String>, // nama file dari client, hanya untuk ditampilkan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>, // dari format hasil deteksi, bukan kiriman client
+
+    // Metadata opsional dari user (form upload atau PATCH /upload/:id)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub company: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fiscal_year: Option<i32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>, // None = upload pribadi
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<mongodb::bson::DateTime>, // Some = di trash, dihapus permanen setelah masa retensi
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan_status: Option<String>, // SCAN_CLEAN / SCAN_INFECTED; None = tidak dipindai (scanner nonaktif, upload lama)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan_signature: Option<String>, // nama malware yang terdeteksi
 
     #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub created_at: DateTime<Utc>,
-}
\ No newline at end of file
+}
+
+pub const SCAN_CLEAN: &str = "clean";
+pub const SCAN_INFECTED: &str = "infected";
+
+impl UserUpload {
+    // File terdeteksi malware: disimpan di folder karantina, tidak boleh dianalisa / diunduh
+    pub fn is_quarantined(&self) -> bool {
+        self.scan_status.as_deref() == Some(SCAN_INFECTED)
+    }
+}
+
+// Field ada tapi null -> Some(None), field tidak dikirim -> None (lewat #[serde(default)])
+fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+// PATCH /upload/:id: field yang tidak dikirim tidak diubah, null atau "" menghapus nilainya
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct UpdateUploadRequest {
+    #[serde(default, deserialize_with = "double_option")]
+    pub title: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub company: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub fiscal_year: Option<Option<i32>>,
+    pub tags: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub notes: Option<Option<String>>,
+}
+
+// GET /uploads?cursor=&limit=&file_type=&status=&tag=&q=&from=&to=&sort=
+#[derive(Debug, Deserialize, Default)]
+pub struct ListUploadsQuery {
+    pub cursor: Option<String>,    // next_cursor dari halaman sebelumnya
+    pub limit: Option<i64>,
+    pub file_type: Option<String>, // pdf, spreadsheet, image, text
+    pub status: Option<String>,    // analyzed | pending
+    pub tag: Option<String>,
+    pub q: Option<String>,         // cari di nama file / title
+    pub from: Option<String>,      // tanggal upload, YYYY-MM-DD atau RFC 3339
+    pub to: Option<String>,
+    pub sort: Option<String>,      // created_at | name | size, prefix "-" = descending
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UploadSort {
+    CreatedAt,
+    Name,
+    Size,
+}
+
+impl UploadSort {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "created_at" => Some(UploadSort::CreatedAt),
+            "name" => Some(UploadSort::Name),
+            "size" => Some(UploadSort::Size),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UploadSort::CreatedAt => "created_at",
+            UploadSort::Name => "name",
+            UploadSort::Size => "size",
+        }
+    }
+}
+
+// Posisi halaman: nilai kunci sort + _id item terakhir. Dikirim ke client sebagai string opaque
+// (base64 dari dokumen BSON) dan hanya berlaku untuk urutan sort yang sama.
+#[derive(Debug, Clone)]
+pub struct UploadCursor {
+    pub value: Bson,
+    pub id: ObjectId,
+}
+
+impl UploadCursor {
+    fn sort_tag(sort: UploadSort, descending: bool) -> String {
+        format!("{}{}", if descending { "-" } else { "" }, sort.as_str())
+    }
+
+    pub fn encode(&self, sort: UploadSort, descending: bool) -> String {
+        let doc = doc! { "s": Self::sort_tag(sort, descending), "v": self.value.clone(), "id": self.id };
+        let mut bytes = Vec::new();
+        let _ = doc.to_writer(&mut bytes);
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    // None = cursor rusak atau dibuat untuk sort lain
+    pub fn decode(raw: &str, sort: UploadSort, descending: bool) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(raw.trim()).ok()?;
+        let doc = Document::from_reader(&mut bytes.as_slice()).ok()?;
+        if doc.get_str("s").ok()? != Self::sort_tag(sort, descending) {
+            return None;
+        }
+        Some(UploadCursor {
+            value: doc.get("v")?.clone(),
+            id: doc.get_object_id("id").ok()?,
+        })
+    }
+}
+
+// ListUploadsQuery yang sudah divalidasi
+#[derive(Debug)]
+pub struct UploadListOptions {
+    pub limit: Option<i64>, // None = tanpa pagination (format lama: semua upload)
+    pub cursor: Option<UploadCursor>,
+    pub file_type: Option<String>,
+    pub analyzed: Option<bool>,
+    pub tag: Option<String>,
+    pub search: Option<String>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>, // inklusif
+    pub sort: UploadSort,
+    pub descending: bool,
+}
+
+pub const ANALYSIS_ANALYZED: &str = "analyzed";
+pub const ANALYSIS_PENDING: &str = "pending";
+
+// Satu baris di GET /uploads: data upload + status analisa dari financial_reports
+#[derive(Debug, Serialize)]
+pub struct UploadListItem {
+    #[serde(flatten)]
+    pub upload: UserUpload,
+    pub analysis_status: &'static str,
+}
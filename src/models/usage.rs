@@ -0,0 +1,17 @@
+// src/models/usage.rs
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+
+// Counter pemakaian per user per bulan (period = "YYYY-MM")
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Usage {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+
+    pub user_id: String,
+    pub period: String,
+    #[serde(default)]
+    pub uploads: i64,
+    #[serde(default)]
+    pub analyses: i64,
+}
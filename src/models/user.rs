@@ -9,6 +9,36 @@ pub struct User {
     pub name: String,
     pub password: String,
     pub plan: String,
+    // Akun lama (sebelum ada verifikasi) dianggap sudah terverifikasi
+    #[serde(default = "default_email_verified")]
+    pub email_verified: bool,
+    // Role global: admin | analyst | viewer
+    #[serde(default = "default_role")]
+    pub role: String,
+
+    // 2FA (TOTP). Secret disimpan saat setup, baru aktif setelah kode pertama diverifikasi.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp_secret: Option<String>,
+    #[serde(default)]
+    pub totp_enabled: bool,
+    // Time step terakhir yang dipakai, agar kode yang sama tidak bisa dipakai ulang
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totp_last_step: Option<i64>,
+    // Hash SHA-256 dari recovery code yang belum dipakai
+    #[serde(default)]
+    pub recovery_codes: Vec<String>,
+
+    // URL /public/{user_id}/avatar/... ; None = belum upload avatar
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+}
+
+fn default_email_verified() -> bool {
+    true
+}
+
+fn default_role() -> String {
+    "analyst".to_string()
 }
 
 #[derive(Debug, Deserialize)]
@@ -16,4 +46,64 @@ pub struct AuthRequest {
     pub email: String,
     pub name: Option<String>,
     pub password: String,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub new_password: String,
+}
+
+// PATCH /auth/me: field yang tidak dikirim tidak diubah
+#[derive(Debug, Deserialize)]
+pub struct UpdateProfileRequest {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub current_password: Option<String>, // wajib saat email diganti (akun dengan password lokal)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub old_password: String,
+    pub new_password: String,
+}
+
+// Akun tanpa password lokal (login via OIDC) tidak perlu mengirim password
+#[derive(Debug, Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: Option<String>,
+}
+
+// Kode TOTP 6 digit atau recovery code
+#[derive(Debug, Deserialize)]
+pub struct TotpCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MfaLoginRequest {
+    pub mfa_token: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DisableTotpRequest {
+    pub password: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdminUpdateUserRequest {
+    pub role: Option<String>,
+    pub plan: Option<String>,
+}
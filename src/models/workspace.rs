@@ -0,0 +1,80 @@
+// src/models/workspace.rs
+use serde::{Deserialize, Serialize};
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use chrono::{DateTime, Utc};
+
+pub const ROLE_OWNER: &str = "owner";
+pub const ROLE_ANALYST: &str = "analyst";
+pub const ROLE_VIEWER: &str = "viewer";
+pub const WORKSPACE_ROLES: [&str; 3] = [ROLE_OWNER, ROLE_ANALYST, ROLE_VIEWER];
+
+// Workspace/organisasi: upload & laporan keuangan di dalamnya bisa dilihat semua member
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Workspace {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+
+    pub name: String,
+    pub owner_id: String,
+
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WorkspaceMember {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+
+    pub workspace_id: String,
+    pub user_id: String,
+    pub role: String, // owner | analyst | viewer
+
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWorkspaceRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InviteMemberRequest {
+    pub email: String,
+    pub role: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMemberRequest {
+    pub role: String,
+}
+
+// Cakupan data untuk satu request: data pribadi user, atau satu workspace.
+// Dipilih lewat header "X-Workspace-Id".
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub user_id: String,
+    pub workspace_id: Option<String>,
+    pub role: String,
+}
+
+impl Scope {
+    pub fn personal(user_id: &str) -> Self {
+        Scope { user_id: user_id.to_string(), workspace_id: None, role: ROLE_OWNER.to_string() }
+    }
+
+    // Filter MongoDB untuk user_uploads / financial_reports.
+    // Data pribadi = milik user dan tidak ada di workspace mana pun (field kosong = null).
+    pub fn filter(&self) -> Document {
+        match &self.workspace_id {
+            Some(ws) => doc! { "workspace_id": ws },
+            None => doc! { "user_id": &self.user_id, "workspace_id": null },
+        }
+    }
+
+    // Viewer hanya boleh membaca
+    pub fn can_write(&self) -> bool {
+        self.role == ROLE_OWNER || self.role == ROLE_ANALYST
+    }
+}
@@ -0,0 +1,60 @@
+use mongodb::{Database, Collection, bson::{doc, oid::ObjectId}};
+use mongodb::options::FindOptions;
+use futures::TryStreamExt;
+use chrono::Utc;
+use crate::models::api_key::ApiKey;
+
+#[derive(Clone)]
+pub struct ApiKeyRepository {
+    pub collection: Collection<ApiKey>,
+}
+
+impl ApiKeyRepository {
+    pub fn new(db: &Database) -> Self {
+        ApiKeyRepository {
+            collection: db.collection("api_keys"),
+        }
+    }
+
+    pub async fn create(&self, key: ApiKey) -> mongodb::error::Result<String> {
+        let result = self.collection.insert_one(key, None).await?;
+        let oid = result.inserted_id.as_object_id()
+            .ok_or_else(|| mongodb::error::Error::custom("Invalid inserted ID"))?;
+        Ok(oid.to_hex())
+    }
+
+    pub async fn find_active_by_hash(&self, key_hash: &str) -> mongodb::error::Result<Option<ApiKey>> {
+        self.collection.find_one(doc! { "key_hash": key_hash, "revoked": false }, None).await
+    }
+
+    pub async fn list_by_user(&self, user_id: &str) -> mongodb::error::Result<Vec<ApiKey>> {
+        let options = FindOptions::builder().sort(doc! { "created_at": -1 }).build();
+        let mut cursor = self.collection.find(doc! { "user_id": user_id, "revoked": false }, options).await?;
+
+        let mut keys = Vec::new();
+        while let Some(key) = cursor.try_next().await? {
+            keys.push(key);
+        }
+        Ok(keys)
+    }
+
+    pub async fn touch(&self, id: &str) -> mongodb::error::Result<()> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        let update = doc! { "$set": { "last_used_at": mongodb::bson::DateTime::from_chrono(Utc::now()) } };
+        self.collection.update_one(doc! { "_id": oid }, update, None).await?;
+        Ok(())
+    }
+
+    pub async fn revoke(&self, id: &str, user_id: &str) -> mongodb::error::Result<u64> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        let filter = doc! { "_id": oid, "user_id": user_id, "revoked": false };
+        let result = self.collection.update_one(filter, doc! { "$set": { "revoked": true } }, None).await?;
+        Ok(result.modified_count)
+    }
+
+    pub async fn revoke_all(&self, user_id: &str) -> mongodb::error::Result<u64> {
+        let filter = doc! { "user_id": user_id, "revoked": false };
+        let result = self.collection.update_many(filter, doc! { "$set": { "revoked": true } }, None).await?;
+        Ok(result.modified_count)
+    }
+}
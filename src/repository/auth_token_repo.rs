@@ -0,0 +1,40 @@
+use mongodb::{Database, Collection, bson::doc};
+use chrono::Utc;
+use crate::models::auth_token::AuthToken;
+
+#[derive(Clone)]
+pub struct AuthTokenRepository {
+    pub collection: Collection<AuthToken>,
+}
+
+impl AuthTokenRepository {
+    pub fn new(db: &Database) -> Self {
+        AuthTokenRepository {
+            collection: db.collection("auth_tokens"),
+        }
+    }
+
+    pub async fn create(&self, token: AuthToken) -> mongodb::error::Result<()> {
+        self.collection.insert_one(token, None).await?;
+        Ok(())
+    }
+
+    // Ambil & tandai token sebagai terpakai dalam satu operasi atomik,
+    // sehingga token yang sama tidak bisa dipakai dua kali
+    pub async fn consume(&self, token_hash: &str, purpose: &str) -> mongodb::error::Result<Option<AuthToken>> {
+        let filter = doc! {
+            "token_hash": token_hash,
+            "purpose": purpose,
+            "used": false,
+            "expires_at": { "$gt": mongodb::bson::DateTime::from_chrono(Utc::now()) },
+        };
+        self.collection.find_one_and_update(filter, doc! { "$set": { "used": true } }, None).await
+    }
+
+    // Token lama untuk tujuan yang sama dibatalkan saat token baru dibuat
+    pub async fn invalidate_for_user(&self, user_id: &str, purpose: &str) -> mongodb::error::Result<u64> {
+        let filter = doc! { "user_id": user_id, "purpose": purpose, "used": false };
+        let result = self.collection.update_many(filter, doc! { "$set": { "used": true } }, None).await?;
+        Ok(result.modified_count)
+    }
+}
@@ -0,0 +1,123 @@
+use chrono::{DateTime, Utc};
+use mongodb::{Database, Collection, bson::{self, doc, oid::ObjectId}};
+use mongodb::options::FindOptions;
+use futures::TryStreamExt;
+use crate::models::data_export::{DataExport, EXPORT_FAILED, EXPORT_PENDING, EXPORT_READY};
+
+#[derive(Clone)]
+pub struct DataExportRepository {
+    pub collection: Collection<DataExport>,
+}
+
+impl DataExportRepository {
+    pub fn new(db: &Database) -> Self {
+        DataExportRepository {
+            collection: db.collection("data_exports"),
+        }
+    }
+
+    pub async fn create(&self, export: DataExport) -> mongodb::error::Result<String> {
+        let result = self.collection.insert_one(export, None).await?;
+        let oid = result.inserted_id.as_object_id()
+            .ok_or_else(|| mongodb::error::Error::custom("Invalid inserted ID"))?;
+        Ok(oid.to_hex())
+    }
+
+    // Export hanya bisa dilihat pemiliknya
+    pub async fn find_for_user(&self, id: &str, user_id: &str) -> mongodb::error::Result<Option<DataExport>> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        self.collection.find_one(doc! { "_id": oid, "user_id": user_id }, None).await
+    }
+
+    pub async fn list_by_user(&self, user_id: &str) -> mongodb::error::Result<Vec<DataExport>> {
+        let options = FindOptions::builder().sort(doc! { "created_at": -1 }).build();
+        let mut cursor = self.collection.find(doc! { "user_id": user_id }, options).await?;
+
+        let mut exports = Vec::new();
+        while let Some(export) = cursor.try_next().await? {
+            exports.push(export);
+        }
+        Ok(exports)
+    }
+
+    // Dipakai untuk mencegah beberapa export berjalan bersamaan untuk user yang sama.
+    // Export pending yang dibuat sebelum `since` dianggap terputus (crash / restart) dan tidak menghalangi.
+    pub async fn has_pending(&self, user_id: &str, since: DateTime<Utc>) -> mongodb::error::Result<bool> {
+        let filter = doc! {
+            "user_id": user_id,
+            "status": EXPORT_PENDING,
+            "created_at": { "$gte": bson::DateTime::from_chrono(since) },
+        };
+        let count = self.collection.count_documents(filter, None).await?;
+        Ok(count > 0)
+    }
+
+    // Export pending yang terputus ditandai gagal agar statusnya tidak menggantung selamanya
+    pub async fn fail_stale_pending(&self, before: DateTime<Utc>) -> mongodb::error::Result<u64> {
+        let result = self.collection.update_many(
+            doc! { "status": EXPORT_PENDING, "created_at": { "$lt": bson::DateTime::from_chrono(before) } },
+            doc! { "$set": {
+                "status": EXPORT_FAILED,
+                "error": "Export interrupted",
+                "completed_at": bson::DateTime::now(),
+            } },
+            None,
+        ).await?;
+        Ok(result.modified_count)
+    }
+
+    // Export kedaluwarsa yang file ZIP-nya masih ada di disk
+    pub async fn find_expired_with_file(&self, now: DateTime<Utc>) -> mongodb::error::Result<Vec<DataExport>> {
+        let filter = doc! {
+            "expires_at": { "$lt": bson::DateTime::from_chrono(now) },
+            "file_path": { "$type": "string" },
+        };
+        let mut cursor = self.collection.find(filter, None).await?;
+
+        let mut exports = Vec::new();
+        while let Some(export) = cursor.try_next().await? {
+            exports.push(export);
+        }
+        Ok(exports)
+    }
+
+    // File ZIP sudah dihapus; record tetap ada sebagai riwayat
+    pub async fn clear_file(&self, id: &ObjectId) -> mongodb::error::Result<()> {
+        self.collection.update_one(doc! { "_id": id }, doc! { "$unset": { "file_path": "" } }, None).await?;
+        Ok(())
+    }
+
+    pub async fn mark_ready(&self, id: &str, file_path: &str, size_bytes: i64) -> mongodb::error::Result<()> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        self.collection.update_one(
+            doc! { "_id": oid },
+            doc! { "$set": {
+                "status": EXPORT_READY,
+                "file_path": file_path,
+                "size_bytes": size_bytes,
+                "completed_at": bson::DateTime::now(),
+            } },
+            None,
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn mark_failed(&self, id: &str, error: &str) -> mongodb::error::Result<()> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        self.collection.update_one(
+            doc! { "_id": oid },
+            doc! { "$set": {
+                "status": EXPORT_FAILED,
+                "error": error,
+                "completed_at": bson::DateTime::now(),
+            } },
+            None,
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn delete_by_user(&self, user_id: &str) -> mongodb::error::Result<u64> {
+        let result = self.collection.delete_many(doc! { "user_id": user_id }, None).await?;
+        Ok(result.deleted_count)
+    }
+}
@@ -0,0 +1,46 @@
+use mongodb::{Database, Collection, bson::doc};
+use chrono::Utc;
+use crate::models::external_identity::{ExternalIdentity, OidcLoginState};
+
+#[derive(Clone)]
+pub struct ExternalIdentityRepository {
+    pub collection: Collection<ExternalIdentity>,
+    pub login_states: Collection<OidcLoginState>,
+}
+
+impl ExternalIdentityRepository {
+    pub fn new(db: &Database) -> Self {
+        ExternalIdentityRepository {
+            collection: db.collection("external_identities"),
+            login_states: db.collection("oidc_login_states"),
+        }
+    }
+
+    pub async fn find(&self, provider: &str, subject: &str) -> mongodb::error::Result<Option<ExternalIdentity>> {
+        self.collection.find_one(doc! { "provider": provider, "subject": subject }, None).await
+    }
+
+    pub async fn create(&self, identity: ExternalIdentity) -> mongodb::error::Result<()> {
+        self.collection.insert_one(identity, None).await?;
+        Ok(())
+    }
+
+    pub async fn delete_by_user(&self, user_id: &str) -> mongodb::error::Result<u64> {
+        let result = self.collection.delete_many(doc! { "user_id": user_id }, None).await?;
+        Ok(result.deleted_count)
+    }
+
+    pub async fn create_login_state(&self, state: OidcLoginState) -> mongodb::error::Result<()> {
+        self.login_states.insert_one(state, None).await?;
+        Ok(())
+    }
+
+    // Ambil & hapus dalam satu operasi atomik: parameter "state" hanya berlaku sekali
+    pub async fn consume_login_state(&self, state_hash: &str) -> mongodb::error::Result<Option<OidcLoginState>> {
+        let filter = doc! {
+            "state_hash": state_hash,
+            "expires_at": { "$gt": mongodb::bson::DateTime::from_chrono(Utc::now()) },
+        };
+        self.login_states.find_one_and_delete(filter, None).await
+    }
+}
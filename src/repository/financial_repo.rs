@@ -1,7 +1,9 @@
-use mongodb::{Database, Collection, options::ReplaceOptions};
-use mongodb::bson::doc;
+use mongodb::{ClientSession, Database, Collection, options::ReplaceOptions};
+use mongodb::bson::{doc, oid::ObjectId, Bson};
+use chrono::{DateTime, Utc};
 use futures::stream::TryStreamExt;
 use crate::models::financial::FinancialRecord;
+use crate::models::workspace::Scope;
 
 #[derive(Clone)]
 pub struct FinancialRepository {
@@ -24,8 +26,10 @@ impl FinancialRepository {
         Ok(())
     }
 
-    pub async fn find_by_user(&self, user_id: &str) -> mongodb::error::Result<Vec<FinancialRecord>> {
-        let filter = doc! { "user_id": user_id };
+    // Hasil analisa dari upload yang ada di trash tidak ikut ditampilkan
+    pub async fn find_in_scope(&self, scope: &Scope) -> mongodb::error::Result<Vec<FinancialRecord>> {
+        let mut filter = scope.filter();
+        filter.insert("deleted_at", Bson::Null);
         let find_options = mongodb::options::FindOptions::builder()
             .sort(doc! { "created_at": -1 })
             .build();
@@ -38,19 +42,79 @@ impl FinancialRepository {
         Ok(results)
     }
 
-    pub async fn count_by_user(&self, user_id: &str) -> mongodb::error::Result<u64> {
-        // Menghitung jumlah dokumen di 'financial_reports' milik user_id ini
-        let filter = doc! { "user_id": user_id };
-        self.collection.count_documents(filter, None).await
+    // Semua hasil analisa milik user, untuk export data akun
+    pub async fn find_by_user(&self, user_id: &str) -> mongodb::error::Result<Vec<FinancialRecord>> {
+        let mut cursor = self.collection.find(doc! { "user_id": user_id }, None).await?;
+        let mut results = Vec::new();
+        while let Some(record) = cursor.try_next().await? {
+            results.push(record);
+        }
+        Ok(results)
+    }
+
+    // Hasil analisa terbaru dari salah satu upload di daftar
+    pub async fn find_latest_by_upload_ids(&self, upload_ids: &[String]) -> mongodb::error::Result<Option<FinancialRecord>> {
+        let options = mongodb::options::FindOneOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .build();
+        self.collection.find_one(doc! { "id_userupload": { "$in": upload_ids } }, options).await
+    }
+
+    // Ikut upload-nya masuk / keluar trash: Some = tandai terhapus, None = restore
+    pub async fn set_deleted_by_upload_id(&self, upload_id: &str, deleted_at: Option<DateTime<Utc>>) -> mongodb::error::Result<u64> {
+        let filter = doc! { "id_userupload": upload_id };
+        let update = match deleted_at {
+            Some(at) => doc! { "$set": { "deleted_at": mongodb::bson::DateTime::from_chrono(at) } },
+            None => doc! { "$unset": { "deleted_at": "" } },
+        };
+        let result = self.collection.update_many(filter, update, None).await?;
+        Ok(result.modified_count)
     }
 
-    pub async fn delete_by_upload_id(&self, upload_id: &str) -> mongodb::error::Result<u64> {
+    // Opsional di dalam transaksi (cascade delete)
+    pub async fn delete_by_upload_id(&self, upload_id: &str, session: Option<&mut ClientSession>) -> mongodb::error::Result<u64> {
         // Query untuk mencari dokumen dengan id_userupload yang cocok
         let filter = doc! { "id_userupload": upload_id };
         
-        let result = self.collection.delete_many(filter, None).await?;
+        let result = match session {
+            Some(session) => self.collection.delete_many_with_session(filter, None, session).await?,
+            None => self.collection.delete_many(filter, None).await?,
+        };
         
         println!("Deleted {} financial records for upload_id: {}", result.deleted_count, upload_id);
         Ok(result.deleted_count)
     }
-}
\ No newline at end of file
+
+    // Financial report yang upload induknya sudah tidak ada: (id record, id_userupload)
+    pub async fn find_orphans(&self) -> mongodb::error::Result<Vec<(ObjectId, String)>> {
+        let pipeline = vec![
+            doc! {
+                "$lookup": {
+                    "from": "user_uploads",
+                    "let": { "upload_oid": { "$convert": { "input": "$id_userupload", "to": "objectId", "onError": null, "onNull": null } } },
+                    "pipeline": [
+                        { "$match": { "$expr": { "$eq": ["$_id", "$$upload_oid"] } } },
+                        { "$project": { "_id": 1 } }
+                    ],
+                    "as": "upload"
+                }
+            },
+            doc! { "$match": { "upload": { "$size": 0 } } },
+            doc! { "$project": { "_id": 1, "id_userupload": 1 } },
+        ];
+
+        let mut cursor = self.collection.aggregate(pipeline, None).await?;
+        let mut orphans = Vec::new();
+        while let Some(doc) = cursor.try_next().await? {
+            if let Ok(id) = doc.get_object_id("_id") {
+                orphans.push((id, doc.get_str("id_userupload").unwrap_or_default().to_string()));
+            }
+        }
+        Ok(orphans)
+    }
+
+    pub async fn delete_by_ids(&self, ids: &[ObjectId]) -> mongodb::error::Result<u64> {
+        let result = self.collection.delete_many(doc! { "_id": { "$in": ids } }, None).await?;
+        Ok(result.deleted_count)
+    }
+}
@@ -0,0 +1,50 @@
+use mongodb::{Database, Collection, bson::{self, doc}, options::{FindOneAndUpdateOptions, ReturnDocument}};
+use chrono::{DateTime, Utc};
+use crate::models::login_attempt::LoginAttempt;
+
+#[derive(Clone)]
+pub struct LoginAttemptRepository {
+    pub collection: Collection<LoginAttempt>,
+}
+
+impl LoginAttemptRepository {
+    pub fn new(db: &Database) -> Self {
+        LoginAttemptRepository {
+            collection: db.collection("login_attempts"),
+        }
+    }
+
+    pub async fn find(&self, key: &str) -> mongodb::error::Result<Option<LoginAttempt>> {
+        self.collection.find_one(doc! { "key": key }, None).await
+    }
+
+    // Tambah 1 kegagalan (upsert) dan kembalikan counter terbaru
+    pub async fn record_failure(&self, key: &str) -> mongodb::error::Result<Option<LoginAttempt>> {
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+        self.collection.find_one_and_update(
+            doc! { "key": key },
+            doc! {
+                "$inc": { "failures": 1_i64 },
+                "$set": { "last_failure_at": bson::DateTime::now() }
+            },
+            options,
+        ).await
+    }
+
+    pub async fn lock(&self, key: &str, until: DateTime<Utc>) -> mongodb::error::Result<()> {
+        self.collection.update_one(
+            doc! { "key": key },
+            doc! { "$set": { "locked_until": bson::DateTime::from_chrono(until) } },
+            None,
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn clear(&self, key: &str) -> mongodb::error::Result<()> {
+        self.collection.delete_one(doc! { "key": key }, None).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+use mongodb::{ClientSession, Database, Collection, IndexModel, bson::{self, doc}, options::{FindOneAndUpdateOptions, IndexOptions, ReturnDocument, UpdateOptions}};
+use futures::TryStreamExt;
+use crate::models::media_blob::MediaBlob;
+use crate::repository::is_duplicate_key;
+
+#[derive(Clone)]
+pub struct MediaBlobRepository {
+    pub collection: Collection<MediaBlob>,
+}
+
+impl MediaBlobRepository {
+    pub fn new(db: &Database) -> Self {
+        MediaBlobRepository {
+            collection: db.collection("media_blobs"),
+        }
+    }
+
+    // Satu record per file fisik; tanpa ini dua upsert paralel bisa membuat dua record ref_count 1
+    pub async fn ensure_indexes(&self) -> mongodb::error::Result<()> {
+        let index = IndexModel::builder()
+            .keys(doc! { "file_path": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.collection.create_index(index, None).await?;
+        Ok(())
+    }
+
+    // Tambah 1 referensi (upsert) dan kembalikan ref_count terbaru
+    pub async fn acquire(&self, file_path: &str, user_id: &str, sha256: &str, size_bytes: i64) -> mongodb::error::Result<i64> {
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+        let filter = doc! { "file_path": file_path };
+        let update = doc! {
+            "$inc": { "ref_count": 1_i64 },
+            "$setOnInsert": {
+                "user_id": user_id,
+                "sha256": sha256,
+                "size_bytes": size_bytes,
+                "created_at": bson::DateTime::now(),
+            }
+        };
+        let blob = match self.collection.find_one_and_update(filter.clone(), update.clone(), options.clone()).await {
+            Ok(blob) => blob,
+            // Kalah balapan insert dengan upload paralel file yang sama: record sudah ada, ulangi sebagai update
+            Err(e) if is_duplicate_key(&e) => self.collection.find_one_and_update(filter, update, options).await?,
+            Err(e) => return Err(e),
+        };
+        Ok(blob.map(|b| b.ref_count).unwrap_or(1))
+    }
+
+    // Kurangi 1 referensi. true = tidak ada lagi upload yang memakai file ini
+    // (record blob sudah dihapus), file fisik boleh dihapus oleh pemanggil.
+    pub async fn release(&self, file_path: &str) -> mongodb::error::Result<bool> {
+        self.release_with(file_path, None).await
+    }
+
+    // Sama dengan release, opsional di dalam transaksi (cascade delete)
+    pub async fn release_with(&self, file_path: &str, mut session: Option<&mut ClientSession>) -> mongodb::error::Result<bool> {
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let filter = doc! { "file_path": file_path };
+        let update = doc! { "$inc": { "ref_count": -1_i64 } };
+        let blob = match session.as_deref_mut() {
+            Some(session) => self.collection.find_one_and_update_with_session(filter, update, options, session).await?,
+            None => self.collection.find_one_and_update(filter, update, options).await?,
+        };
+
+        let Some(blob) = blob else { return Ok(true) };
+        if blob.ref_count > 0 {
+            return Ok(false);
+        }
+
+        // Hanya hapus jika belum di-acquire lagi oleh upload baru di antara dua query ini
+        let filter = doc! { "file_path": file_path, "ref_count": { "$lte": 0_i64 } };
+        let result = match session {
+            Some(session) => self.collection.delete_one_with_session(filter, None, session).await?,
+            None => self.collection.delete_one(filter, None).await?,
+        };
+        Ok(result.deleted_count > 0)
+    }
+
+    // Semua blob, untuk command reconcile
+    pub async fn find_all(&self) -> mongodb::error::Result<Vec<MediaBlob>> {
+        let mut cursor = self.collection.find(None, None).await?;
+        let mut blobs = Vec::new();
+        while let Some(blob) = cursor.try_next().await? {
+            blobs.push(blob);
+        }
+        Ok(blobs)
+    }
+
+    // Samakan ref_count dengan jumlah upload yang benar-benar memakai file (reconcile).
+    // Blob yang belum tercatat dibuat ulang.
+    pub async fn set_ref_count(&self, file_path: &str, user_id: &str, sha256: &str, size_bytes: i64, ref_count: i64) -> mongodb::error::Result<()> {
+        let options = UpdateOptions::builder().upsert(true).build();
+        self.collection.update_one(
+            doc! { "file_path": file_path },
+            doc! {
+                "$set": { "ref_count": ref_count },
+                "$setOnInsert": {
+                    "user_id": user_id,
+                    "sha256": sha256,
+                    "size_bytes": size_bytes,
+                    "created_at": bson::DateTime::now(),
+                }
+            },
+            options,
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn delete_by_file_path(&self, file_path: &str) -> mongodb::error::Result<u64> {
+        let result = self.collection.delete_one(doc! { "file_path": file_path }, None).await?;
+        Ok(result.deleted_count)
+    }
+}
@@ -2,4 +2,25 @@
 
 pub mod user_repo;
 pub mod upload_repo;
-pub mod financial_repo; // <--- TAMBAHKAN BARIS INI
\ No newline at end of file
+pub mod financial_repo; // <--- TAMBAHKAN BARIS INI
+pub mod session_repo;
+pub mod auth_token_repo;
+pub mod api_key_repo;
+pub mod usage_repo;
+pub mod workspace_repo;
+pub mod login_attempt_repo;
+pub mod external_identity_repo;
+pub mod data_export_repo;
+pub mod media_blob_repo;
+
+use mongodb::error::{Error, ErrorKind, WriteFailure};
+
+// Error E11000: dokumen bentrok dengan unique index (mis. dua upsert paralel untuk key yang sama)
+pub fn is_duplicate_key(e: &Error) -> bool {
+    const DUPLICATE_KEY: i32 = 11000;
+    match e.kind.as_ref() {
+        ErrorKind::Write(WriteFailure::WriteError(we)) => we.code == DUPLICATE_KEY,
+        ErrorKind::Command(ce) => ce.code == DUPLICATE_KEY,
+        _ => false,
+    }
+}
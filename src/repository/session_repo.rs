@@ -0,0 +1,115 @@
+use mongodb::{Database, Collection, IndexModel, bson::{doc, oid::ObjectId}};
+use mongodb::options::{FindOptions, IndexOptions};
+use futures::TryStreamExt;
+use chrono::Utc;
+use crate::models::session::Session;
+
+#[derive(Clone)]
+pub struct SessionRepository {
+    pub collection: Collection<Session>,
+}
+
+impl SessionRepository {
+    pub fn new(db: &Database) -> Self {
+        SessionRepository {
+            collection: db.collection("sessions"),
+        }
+    }
+
+    // Refresh token dicari lewat hash-nya, satu hash hanya boleh milik satu sesi
+    pub async fn ensure_indexes(&self) -> mongodb::error::Result<()> {
+        let index = IndexModel::builder()
+            .keys(doc! { "refresh_token_hash": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.collection.create_index(index, None).await?;
+        Ok(())
+    }
+
+    pub async fn create(&self, session: Session) -> mongodb::error::Result<String> {
+        let result = self.collection.insert_one(session, None).await?;
+        let oid = result.inserted_id.as_object_id()
+            .ok_or_else(|| mongodb::error::Error::custom("Invalid inserted ID"))?;
+        Ok(oid.to_hex())
+    }
+
+    // Sesi aktif = belum di-revoke dan belum expired
+    pub async fn find_active(&self, id: &str) -> mongodb::error::Result<Option<Session>> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        let filter = doc! {
+            "_id": oid,
+            "revoked": false,
+            "expires_at": { "$gt": mongodb::bson::DateTime::from_chrono(Utc::now()) },
+        };
+        self.collection.find_one(filter, None).await
+    }
+
+    pub async fn find_active_by_refresh_hash(&self, refresh_token_hash: &str) -> mongodb::error::Result<Option<Session>> {
+        let filter = doc! {
+            "refresh_token_hash": refresh_token_hash,
+            "revoked": false,
+            "expires_at": { "$gt": mongodb::bson::DateTime::from_chrono(Utc::now()) },
+        };
+        self.collection.find_one(filter, None).await
+    }
+
+    pub async fn list_active_by_user(&self, user_id: &str) -> mongodb::error::Result<Vec<Session>> {
+        let filter = doc! {
+            "user_id": user_id,
+            "revoked": false,
+            "expires_at": { "$gt": mongodb::bson::DateTime::from_chrono(Utc::now()) },
+        };
+        let options = FindOptions::builder().sort(doc! { "last_used_at": -1 }).build();
+
+        let mut cursor = self.collection.find(filter, options).await?;
+        let mut sessions = Vec::new();
+        while let Some(session) = cursor.try_next().await? {
+            sessions.push(session);
+        }
+        Ok(sessions)
+    }
+
+    pub async fn touch(&self, id: &str) -> mongodb::error::Result<()> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        let update = doc! { "$set": { "last_used_at": mongodb::bson::DateTime::from_chrono(Utc::now()) } };
+        self.collection.update_one(doc! { "_id": oid }, update, None).await?;
+        Ok(())
+    }
+
+    // Rotasi refresh token: hash lama diganti, sehingga token lama tidak bisa dipakai lagi.
+    // Filter ikut hash lama: dua request refresh dengan token yang sama hanya satu yang menang.
+    // false = token sudah dirotasi request lain (reuse), pemanggil wajib me-revoke sesi.
+    pub async fn rotate_refresh_token(&self, id: &str, old_hash: &str, new_hash: &str, ip: &str, user_agent: &str) -> mongodb::error::Result<bool> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        let filter = doc! { "_id": oid, "refresh_token_hash": old_hash, "revoked": false };
+        let update = doc! { "$set": {
+            "refresh_token_hash": new_hash,
+            "ip": ip,
+            "user_agent": user_agent,
+            "last_used_at": mongodb::bson::DateTime::from_chrono(Utc::now()),
+        } };
+        let result = self.collection.update_one(filter, update, None).await?;
+        Ok(result.matched_count > 0)
+    }
+
+    pub async fn revoke(&self, id: &str, user_id: &str) -> mongodb::error::Result<u64> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        let filter = doc! { "_id": oid, "user_id": user_id, "revoked": false };
+        let result = self.collection.update_one(filter, doc! { "$set": { "revoked": true } }, None).await?;
+        Ok(result.modified_count)
+    }
+
+    pub async fn revoke_all(&self, user_id: &str) -> mongodb::error::Result<u64> {
+        let filter = doc! { "user_id": user_id, "revoked": false };
+        let result = self.collection.update_many(filter, doc! { "$set": { "revoked": true } }, None).await?;
+        Ok(result.modified_count)
+    }
+
+    // Ganti password: sesi lain di-revoke, sesi yang sedang dipakai tetap aktif
+    pub async fn revoke_all_except(&self, user_id: &str, keep_id: &str) -> mongodb::error::Result<u64> {
+        let keep_oid = ObjectId::parse_str(keep_id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        let filter = doc! { "user_id": user_id, "revoked": false, "_id": { "$ne": keep_oid } };
+        let result = self.collection.update_many(filter, doc! { "$set": { "revoked": true } }, None).await?;
+        Ok(result.modified_count)
+    }
+}
@@ -1,6 +1,9 @@
-use mongodb::{Database, Collection, bson::{doc, oid::ObjectId}};
-use crate::models::upload::UserUpload;
+use mongodb::{ClientSession, Database, Collection, bson::{self, doc, oid::ObjectId, Bson, Document}};
+use mongodb::options::{FindOneAndUpdateOptions, ReturnDocument};
+use crate::models::upload::{UploadCursor, UploadListItem, UploadListOptions, UploadSort, UserUpload, ANALYSIS_ANALYZED, ANALYSIS_PENDING};
+use crate::models::workspace::Scope;
 use futures::TryStreamExt;
+use chrono::{DateTime, Utc};
 
 #[derive(Clone)]
 pub struct UploadRepository {
@@ -14,13 +17,16 @@ impl UploadRepository {
         }
     }
 
-    pub async fn create_upload(&self, upload: UserUpload) -> mongodb::error::Result<()> {
-        self.collection.insert_one(upload, None).await?;
-        Ok(())
+    pub async fn create_upload(&self, upload: UserUpload) -> mongodb::error::Result<String> {
+        let result = self.collection.insert_one(upload, None).await?;
+        let oid = result.inserted_id.as_object_id()
+            .ok_or_else(|| mongodb::error::Error::custom("Invalid inserted ID"))?;
+        Ok(oid.to_hex())
     }
 
-    pub async fn find_by_user(&self, user_id: &str) -> mongodb::error::Result<Vec<UserUpload>> {
-        let filter = doc! { "user_id": user_id };
+    // Semua upload di scope, termasuk yang ada di trash (untuk hapus akun / workspace)
+    pub async fn find_in_scope(&self, scope: &Scope) -> mongodb::error::Result<Vec<UserUpload>> {
+        let filter = scope.filter();
         let mut cursor = self.collection.find(filter, None).await?;
         
         let mut uploads = Vec::new();
@@ -30,25 +36,166 @@ impl UploadRepository {
         Ok(uploads)
     }
 
-    // 1. Cari berdasarkan ID (Penting untuk mendapatkan nama file sebelum dihapus)
-    pub async fn find_by_id(&self, id: &str) -> mongodb::error::Result<Option<UserUpload>> {
+    // GET /uploads: satu halaman upload di scope, dengan filter, sort, dan status analisa
+    // (join ke financial_reports seperti get_uploads_stats). Mengembalikan next_cursor jika masih ada halaman berikutnya.
+    pub async fn list_in_scope(&self, scope: &Scope, options: &UploadListOptions) -> mongodb::error::Result<(Vec<UploadListItem>, Option<String>)> {
+        let mut filter = active_filter(scope);
+        if let Some(file_type) = &options.file_type {
+            filter.insert("file_type", file_type);
+        }
+        if let Some(tag) = &options.tag {
+            filter.insert("tags", tag);
+        }
+        if let Some(search) = &options.search {
+            let pattern = bson::Regex { pattern: escape_regex(search), options: "i".to_string() };
+            filter.insert("$or", vec![
+                doc! { "original_name": pattern.clone() },
+                doc! { "file_name": pattern.clone() },
+                doc! { "title": pattern },
+            ]);
+        }
+        let mut created_at = Document::new();
+        if let Some(from) = options.created_from {
+            created_at.insert("$gte", bson::DateTime::from_chrono(from));
+        }
+        if let Some(to) = options.created_to {
+            created_at.insert("$lte", bson::DateTime::from_chrono(to));
+        }
+        if !created_at.is_empty() {
+            filter.insert("created_at", created_at);
+        }
+
+        // Kunci sort dihitung di pipeline agar upload lama (tanpa original_name / size_bytes) tetap terurut
+        let sort_key: Bson = match options.sort {
+            UploadSort::CreatedAt => Bson::String("$created_at".to_string()),
+            UploadSort::Name => Bson::Document(doc! { "$toLower": { "$ifNull": ["$original_name", "$file_name"] } }),
+            UploadSort::Size => Bson::Document(doc! { "$ifNull": ["$size_bytes", 0_i64] }),
+        };
+        let (direction, cmp) = if options.descending { (-1, "$lt") } else { (1, "$gt") };
+
+        let lookup_status = [
+            doc! {
+                "$lookup": {
+                    "from": "financial_reports",
+                    "let": { "upload_id": { "$toString": "$_id" } },
+                    "pipeline": [
+                        { "$match": { "$expr": { "$eq": ["$id_userupload", "$$upload_id"] } } },
+                        { "$limit": 1 },
+                        { "$project": { "_id": 1 } }
+                    ],
+                    "as": "matched_financial"
+                }
+            },
+            doc! { "$addFields": { "analyzed": { "$gt": [{ "$size": "$matched_financial" }, 0] } } },
+        ];
+
+        let mut pipeline = vec![
+            doc! { "$match": filter },
+            doc! { "$addFields": { "sort_key": sort_key } },
+        ];
+        if let Some(cursor) = &options.cursor {
+            pipeline.push(doc! { "$match": { "$or": [
+                { "sort_key": { cmp: cursor.value.clone() } },
+                { "sort_key": cursor.value.clone(), "_id": { cmp: cursor.id } },
+            ] } });
+        }
+        // Filter status butuh join sebelum limit; tanpa filter, join cukup untuk satu halaman
+        if let Some(analyzed) = options.analyzed {
+            pipeline.extend(lookup_status.clone());
+            pipeline.push(doc! { "$match": { "analyzed": analyzed } });
+        }
+        pipeline.push(doc! { "$sort": { "sort_key": direction, "_id": direction } });
+        if let Some(limit) = options.limit {
+            pipeline.push(doc! { "$limit": limit + 1 });
+        }
+        if options.analyzed.is_none() {
+            pipeline.extend(lookup_status);
+        }
+
+        let mut cursor = self.collection.aggregate(pipeline, None).await?;
+        let mut docs = Vec::new();
+        while let Some(doc) = cursor.try_next().await? {
+            docs.push(doc);
+        }
+
+        // Item ekstra (limit + 1) hanya penanda ada halaman berikutnya
+        let has_more = options.limit.is_some_and(|limit| docs.len() as i64 > limit);
+        if let Some(limit) = options.limit {
+            docs.truncate(limit as usize);
+        }
+        let next_cursor = match docs.last() {
+            Some(last) if has_more => Some(UploadCursor {
+                value: last.get("sort_key").cloned().unwrap_or(Bson::Null),
+                id: last.get_object_id("_id").map_err(|_| mongodb::error::Error::custom("Invalid upload ID"))?,
+            }.encode(options.sort, options.descending)),
+            _ => None,
+        };
+
+        let mut items = Vec::with_capacity(docs.len());
+        for mut doc in docs {
+            let analyzed = doc.get_bool("analyzed").unwrap_or(false);
+            for helper in ["sort_key", "matched_financial", "analyzed"] {
+                doc.remove(helper);
+            }
+            items.push(UploadListItem {
+                upload: bson::from_document(doc)?,
+                analysis_status: if analyzed { ANALYSIS_ANALYZED } else { ANALYSIS_PENDING },
+            });
+        }
+        Ok((items, next_cursor))
+    }
+
+    // 1. Cari berdasarkan ID di dalam scope user/workspace (Penting untuk mendapatkan nama file sebelum dihapus)
+    // Upload di luar scope atau di trash dianggap tidak ada (None)
+    pub async fn find_in_scope_by_id(&self, id: &str, scope: &Scope) -> mongodb::error::Result<Option<UserUpload>> {
         let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
-        self.collection.find_one(doc! { "_id": oid }, None).await
+        let mut filter = active_filter(scope);
+        filter.insert("_id", oid);
+        self.collection.find_one(filter, None).await
     }
 
-    pub async fn count_by_user(&self, user_id: &str) -> mongodb::error::Result<u64> {
-        // Filter sesuai dengan field di model UserUpload Anda
-        let filter = doc! { "user_id": user_id };
-        
-        // Mengembalikan jumlah dokumen saja
-        let count = self.collection.count_documents(filter, None).await?;
-        Ok(count)
+    // Semua upload yang dibuat user (pribadi maupun di workspace), untuk export data akun
+    pub async fn find_by_user(&self, user_id: &str) -> mongodb::error::Result<Vec<UserUpload>> {
+        let mut cursor = self.collection.find(doc! { "user_id": user_id }, None).await?;
+
+        let mut uploads = Vec::new();
+        while let Some(doc) = cursor.try_next().await? {
+            uploads.push(doc);
+        }
+        Ok(uploads)
+    }
+
+    // Dipakai guard /public untuk file workspace yang di-upload member lain.
+    // Satu file fisik bisa dipakai beberapa upload (isi identik), jadi kembalikan semua workspace-nya.
+    // Upload di trash tidak memberi akses (sama seperti active_filter).
+    pub async fn find_workspace_ids_by_file_path(&self, file_path: &str) -> mongodb::error::Result<Vec<String>> {
+        let values = self.collection.distinct(
+            "workspace_id",
+            doc! { "file_path": file_path, "workspace_id": { "$type": "string" }, "deleted_at": Bson::Null },
+            None,
+        ).await?;
+        Ok(values.into_iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+    }
+
+    // ID upload lain di scope yang sama dengan isi file identik (untuk pakai ulang hasil analisa)
+    pub async fn find_ids_by_sha256_in_scope(&self, sha256: &str, scope: &Scope) -> mongodb::error::Result<Vec<String>> {
+        let mut filter = active_filter(scope);
+        filter.insert("sha256", sha256);
+        let mut cursor = self.collection.find(filter, None).await?;
+
+        let mut ids = Vec::new();
+        while let Some(upload) = cursor.try_next().await? {
+            if let Some(oid) = upload.id {
+                ids.push(oid.to_hex());
+            }
+        }
+        Ok(ids)
     }
 
-    pub async fn get_uploads_stats(&self, user_id: &str) -> mongodb::error::Result<serde_json::Value> {
+    pub async fn get_uploads_stats(&self, scope: &Scope) -> mongodb::error::Result<serde_json::Value> {
         let pipeline = vec![
-            // 1. Match user_id
-            doc! { "$match": { "user_id": user_id } },
+            // 1. Match user_id / workspace_id (tanpa upload di trash)
+            doc! { "$match": active_filter(scope) },
             
             // 2. Lookup/Join dengan financial_reports
             doc! {
@@ -94,10 +241,133 @@ impl UploadRepository {
     }
 
 
-    // 2. Hapus Record dari DB
-    pub async fn delete(&self, id: &str) -> mongodb::error::Result<u64> {
+    // Ubah metadata upload di scope user/workspace, kembalikan dokumen terbaru (None = tidak ditemukan)
+    pub async fn update_metadata_in_scope(&self, id: &str, scope: &Scope, set: Document, unset: Document) -> mongodb::error::Result<Option<UserUpload>> {
         let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
-        let result = self.collection.delete_one(doc! { "_id": oid }, None).await?;
-        Ok(result.deleted_count)
+        let mut filter = active_filter(scope);
+        filter.insert("_id", oid);
+
+        let mut update = Document::new();
+        if !set.is_empty() {
+            update.insert("$set", set);
+        }
+        if !unset.is_empty() {
+            update.insert("$unset", unset);
+        }
+
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        self.collection.find_one_and_update(filter, update, options).await
+    }
+
+    // Pindahkan ke trash: upload tetap ada (beserta file fisiknya) sampai di-restore atau di-purge
+    pub async fn soft_delete_in_scope(&self, id: &str, scope: &Scope, deleted_at: DateTime<Utc>) -> mongodb::error::Result<u64> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        let mut filter = active_filter(scope);
+        filter.insert("_id", oid);
+        let update = doc! { "$set": { "deleted_at": bson::DateTime::from_chrono(deleted_at) } };
+        let result = self.collection.update_one(filter, update, None).await?;
+        Ok(result.modified_count)
+    }
+
+    // Keluarkan dari trash (hanya upload yang memang ada di trash)
+    pub async fn restore_in_scope(&self, id: &str, scope: &Scope) -> mongodb::error::Result<Option<UserUpload>> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        let mut filter = trash_filter(scope);
+        filter.insert("_id", oid);
+
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        self.collection.find_one_and_update(filter, doc! { "$unset": { "deleted_at": "" } }, options).await
+    }
+
+    // Isi trash di scope, yang terakhir dihapus lebih dulu
+    pub async fn find_trash_in_scope(&self, scope: &Scope) -> mongodb::error::Result<Vec<UserUpload>> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "deleted_at": -1 })
+            .build();
+        let mut cursor = self.collection.find(trash_filter(scope), options).await?;
+
+        let mut uploads = Vec::new();
+        while let Some(doc) = cursor.try_next().await? {
+            uploads.push(doc);
+        }
+        Ok(uploads)
     }
-}
\ No newline at end of file
+
+    pub async fn find_trash_in_scope_by_id(&self, id: &str, scope: &Scope) -> mongodb::error::Result<Option<UserUpload>> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        let mut filter = trash_filter(scope);
+        filter.insert("_id", oid);
+        self.collection.find_one(filter, None).await
+    }
+
+    // Upload di trash yang masa retensinya habis (untuk job purge), maksimal `limit` per putaran
+    pub async fn find_deleted_before(&self, cutoff: DateTime<Utc>, limit: i64) -> mongodb::error::Result<Vec<UserUpload>> {
+        let filter = doc! { "deleted_at": { "$lt": bson::DateTime::from_chrono(cutoff) } };
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "deleted_at": 1 })
+            .limit(limit)
+            .build();
+        let mut cursor = self.collection.find(filter, options).await?;
+
+        let mut uploads = Vec::new();
+        while let Some(doc) = cursor.try_next().await? {
+            uploads.push(doc);
+        }
+        Ok(uploads)
+    }
+
+    // Hapus permanen satu upload (langkah pertama cascade delete, bisa di dalam transaksi).
+    // `only_trashed` = hanya jika masih di trash. false = upload sudah tidak ada / sudah di-restore.
+    pub async fn delete_by_id(&self, id: &str, only_trashed: bool, session: Option<&mut ClientSession>) -> mongodb::error::Result<bool> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        let mut filter = doc! { "_id": oid };
+        if only_trashed {
+            filter.insert("deleted_at", doc! { "$type": "date" });
+        }
+        let result = match session {
+            Some(session) => self.collection.delete_one_with_session(filter, None, session).await?,
+            None => self.collection.delete_one(filter, None).await?,
+        };
+        Ok(result.deleted_count > 0)
+    }
+
+    // Semua upload (termasuk di trash), untuk command reconcile
+    pub async fn find_all(&self) -> mongodb::error::Result<Vec<UserUpload>> {
+        let mut cursor = self.collection.find(None, None).await?;
+
+        let mut uploads = Vec::new();
+        while let Some(doc) = cursor.try_next().await? {
+            uploads.push(doc);
+        }
+        Ok(uploads)
+    }
+}
+
+// Upload aktif (belum di trash) di scope user/workspace
+fn active_filter(scope: &Scope) -> Document {
+    let mut filter = scope.filter();
+    filter.insert("deleted_at", Bson::Null);
+    filter
+}
+
+fn trash_filter(scope: &Scope) -> Document {
+    let mut filter = scope.filter();
+    filter.insert("deleted_at", doc! { "$type": "date" });
+    filter
+}
+
+// Input pencarian dipakai sebagai teks biasa di $regex
+fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.^$|?*+()[]{}/".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,85 @@
+use mongodb::{Database, Collection, IndexModel, bson::doc, options::{IndexOptions, UpdateOptions}};
+use crate::models::usage::Usage;
+use crate::repository::is_duplicate_key;
+
+pub const USAGE_UPLOADS: &str = "uploads";
+pub const USAGE_ANALYSES: &str = "analyses";
+
+#[derive(Clone)]
+pub struct UsageRepository {
+    pub collection: Collection<Usage>,
+}
+
+impl UsageRepository {
+    pub fn new(db: &Database) -> Self {
+        UsageRepository {
+            collection: db.collection("usage_counters"),
+        }
+    }
+
+    // Satu counter per user per periode; dibutuhkan increment_within agar upsert paralel tidak membuat dokumen ganda
+    pub async fn ensure_indexes(&self) -> mongodb::error::Result<()> {
+        let index = IndexModel::builder()
+            .keys(doc! { "user_id": 1, "period": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.collection.create_index(index, None).await?;
+        Ok(())
+    }
+
+    // Belum ada dokumen = belum ada pemakaian di periode ini
+    pub async fn get(&self, user_id: &str, period: &str) -> mongodb::error::Result<Usage> {
+        let usage = self.collection.find_one(doc! { "user_id": user_id, "period": period }, None).await?;
+        Ok(usage.unwrap_or_else(|| Usage {
+            user_id: user_id.to_string(),
+            period: period.to_string(),
+            ..Default::default()
+        }))
+    }
+
+    pub async fn increment(&self, user_id: &str, period: &str, field: &str) -> mongodb::error::Result<()> {
+        let options = UpdateOptions::builder().upsert(true).build();
+        self.collection.update_one(
+            doc! { "user_id": user_id, "period": period },
+            doc! { "$inc": { field: 1_i64 } },
+            options,
+        ).await?;
+        Ok(())
+    }
+
+    // Increment hanya jika counter masih di bawah `limit`, atomik terhadap request paralel.
+    // false = limit sudah tercapai, tidak ada yang diubah.
+    pub async fn increment_within(&self, user_id: &str, period: &str, field: &str, limit: i64) -> mongodb::error::Result<bool> {
+        if limit <= 0 {
+            return Ok(false);
+        }
+        let filter = doc! {
+            "user_id": user_id,
+            "period": period,
+            "$or": [{ field: { "$lt": limit } }, { field: { "$exists": false } }],
+        };
+        let update = doc! { "$inc": { field: 1_i64 } };
+
+        let options = UpdateOptions::builder().upsert(true).build();
+        match self.collection.update_one(filter.clone(), update.clone(), options).await {
+            Ok(_) => Ok(true),
+            // Dokumen sudah ada tapi tidak cocok filter (limit tercapai), atau baru dibuat request lain:
+            // upsert bentrok dengan unique index, ulangi tanpa upsert
+            Err(e) if is_duplicate_key(&e) => {
+                let result = self.collection.update_one(filter, update, None).await?;
+                Ok(result.matched_count > 0)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Batalkan satu increment (mis. file gagal disimpan setelah kuota dipakai)
+    pub async fn decrement(&self, user_id: &str, period: &str, field: &str) -> mongodb::error::Result<()> {
+        self.collection.update_one(
+            doc! { "user_id": user_id, "period": period, field: { "$gt": 0_i64 } },
+            doc! { "$inc": { field: -1_i64 } },
+            None,
+        ).await?;
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
-use mongodb::{Database, Collection, bson::doc};
+use mongodb::{Database, Collection, IndexModel, bson::{doc, oid::ObjectId, Document}};
+use mongodb::options::{FindOptions, IndexOptions};
+use futures::TryStreamExt;
 use crate::models::user::User;
 
 pub struct UserRepository {
@@ -12,12 +14,147 @@ impl UserRepository {
         }
     }
 
+    // Satu akun per email; register / ganti email paralel ditolak oleh database
+    pub async fn ensure_indexes(&self) -> mongodb::error::Result<()> {
+        let index = IndexModel::builder()
+            .keys(doc! { "email": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.collection.create_index(index, None).await?;
+        Ok(())
+    }
+
     pub async fn find_by_email(&self, email: &str) -> Option<User> {
         self.collection.find_one(doc! { "email": email }, None).await.ok().flatten()
     }
 
-    pub async fn create_user(&self, user: User) -> mongodb::error::Result<()> {
-        self.collection.insert_one(user, None).await?;
+    pub async fn find_by_id(&self, id: &str) -> Option<User> {
+        let oid = ObjectId::parse_str(id).ok()?;
+        self.collection.find_one(doc! { "_id": oid }, None).await.ok().flatten()
+    }
+
+    pub async fn create_user(&self, user: User) -> mongodb::error::Result<String> {
+        let result = self.collection.insert_one(user, None).await?;
+        let oid = result.inserted_id.as_object_id()
+            .ok_or_else(|| mongodb::error::Error::custom("Invalid inserted ID"))?;
+        Ok(oid.to_hex())
+    }
+
+    pub async fn set_email_verified(&self, id: &str) -> mongodb::error::Result<()> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        self.collection.update_one(doc! { "_id": oid }, doc! { "$set": { "email_verified": true } }, None).await?;
+        Ok(())
+    }
+
+    pub async fn update_password(&self, id: &str, password_hash: &str) -> mongodb::error::Result<()> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        self.collection.update_one(doc! { "_id": oid }, doc! { "$set": { "password": password_hash } }, None).await?;
+        Ok(())
+    }
+
+    pub async fn list_all(&self) -> mongodb::error::Result<Vec<User>> {
+        let options = FindOptions::builder().sort(doc! { "email": 1 }).build();
+        let mut cursor = self.collection.find(None, options).await?;
+
+        let mut users = Vec::new();
+        while let Some(user) = cursor.try_next().await? {
+            users.push(user);
+        }
+        Ok(users)
+    }
+
+    // Dipakai admin untuk mengubah role/plan. Mengembalikan jumlah dokumen yang cocok.
+    pub async fn update_fields(&self, id: &str, fields: Document) -> mongodb::error::Result<u64> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        let result = self.collection.update_one(doc! { "_id": oid }, doc! { "$set": fields }, None).await?;
+        Ok(result.matched_count)
+    }
+
+    // Bootstrap admin dari env ADMIN_EMAILS
+    pub async fn promote_admins(&self, emails: &[String], role: &str) -> mongodb::error::Result<u64> {
+        if emails.is_empty() {
+            return Ok(0);
+        }
+        let result = self.collection.update_many(
+            doc! { "email": { "$in": emails } },
+            doc! { "$set": { "role": role } },
+            None,
+        ).await?;
+        Ok(result.modified_count)
+    }
+
+    // Simpan secret baru (belum aktif sampai kode pertama terverifikasi)
+    pub async fn set_totp_secret(&self, id: &str, secret: &str) -> mongodb::error::Result<()> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        self.collection.update_one(
+            doc! { "_id": oid },
+            doc! { "$set": { "totp_secret": secret, "totp_enabled": false, "recovery_codes": [] }, "$unset": { "totp_last_step": "" } },
+            None,
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn enable_totp(&self, id: &str, recovery_code_hashes: Vec<String>) -> mongodb::error::Result<()> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        self.collection.update_one(
+            doc! { "_id": oid },
+            doc! { "$set": { "totp_enabled": true, "recovery_codes": recovery_code_hashes } },
+            None,
+        ).await?;
         Ok(())
     }
-}
\ No newline at end of file
+
+    pub async fn disable_totp(&self, id: &str) -> mongodb::error::Result<()> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        self.collection.update_one(
+            doc! { "_id": oid },
+            doc! {
+                "$set": { "totp_enabled": false, "recovery_codes": [] },
+                "$unset": { "totp_secret": "", "totp_last_step": "" }
+            },
+            None,
+        ).await?;
+        Ok(())
+    }
+
+    pub async fn set_recovery_codes(&self, id: &str, recovery_code_hashes: Vec<String>) -> mongodb::error::Result<()> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        self.collection.update_one(
+            doc! { "_id": oid },
+            doc! { "$set": { "recovery_codes": recovery_code_hashes } },
+            None,
+        ).await?;
+        Ok(())
+    }
+
+    // Atomic: true hanya jika time step lebih baru dari yang terakhir dipakai (anti-replay)
+    pub async fn claim_totp_step(&self, id: &str, step: i64) -> mongodb::error::Result<bool> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        let filter = doc! {
+            "_id": oid,
+            "$or": [
+                { "totp_last_step": { "$exists": false } },
+                { "totp_last_step": { "$lt": step } }
+            ]
+        };
+        let result = self.collection.update_one(filter, doc! { "$set": { "totp_last_step": step } }, None).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    // Atomic: recovery code dihapus saat dipakai, sehingga hanya berlaku sekali
+    pub async fn consume_recovery_code(&self, id: &str, code_hash: &str) -> mongodb::error::Result<bool> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        let result = self.collection.update_one(
+            doc! { "_id": oid, "recovery_codes": code_hash },
+            doc! { "$pull": { "recovery_codes": code_hash } },
+            None,
+        ).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    pub async fn delete_user(&self, id: &str) -> mongodb::error::Result<u64> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        let result = self.collection.delete_one(doc! { "_id": oid }, None).await?;
+        Ok(result.deleted_count)
+    }
+}
@@ -0,0 +1,87 @@
+use mongodb::{Database, Collection, bson::{doc, oid::ObjectId}};
+use futures::TryStreamExt;
+use crate::models::workspace::{Workspace, WorkspaceMember};
+
+#[derive(Clone)]
+pub struct WorkspaceRepository {
+    pub collection: Collection<Workspace>,
+    pub members: Collection<WorkspaceMember>,
+}
+
+impl WorkspaceRepository {
+    pub fn new(db: &Database) -> Self {
+        WorkspaceRepository {
+            collection: db.collection("workspaces"),
+            members: db.collection("workspace_members"),
+        }
+    }
+
+    pub async fn create(&self, workspace: Workspace) -> mongodb::error::Result<String> {
+        let result = self.collection.insert_one(workspace, None).await?;
+        let oid = result.inserted_id.as_object_id()
+            .ok_or_else(|| mongodb::error::Error::custom("Invalid inserted ID"))?;
+        Ok(oid.to_hex())
+    }
+
+    pub async fn find_by_id(&self, id: &str) -> mongodb::error::Result<Option<Workspace>> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        self.collection.find_one(doc! { "_id": oid }, None).await
+    }
+
+    // Semua workspace tempat user menjadi member, beserta role-nya
+    pub async fn list_for_user(&self, user_id: &str) -> mongodb::error::Result<Vec<(Workspace, String)>> {
+        let mut cursor = self.members.find(doc! { "user_id": user_id }, None).await?;
+        let mut result = Vec::new();
+        while let Some(member) = cursor.try_next().await? {
+            if let Some(workspace) = self.find_by_id(&member.workspace_id).await? {
+                result.push((workspace, member.role));
+            }
+        }
+        Ok(result)
+    }
+
+    pub async fn find_member(&self, workspace_id: &str, user_id: &str) -> mongodb::error::Result<Option<WorkspaceMember>> {
+        self.members.find_one(doc! { "workspace_id": workspace_id, "user_id": user_id }, None).await
+    }
+
+    pub async fn list_members(&self, workspace_id: &str) -> mongodb::error::Result<Vec<WorkspaceMember>> {
+        let mut cursor = self.members.find(doc! { "workspace_id": workspace_id }, None).await?;
+        let mut members = Vec::new();
+        while let Some(member) = cursor.try_next().await? {
+            members.push(member);
+        }
+        Ok(members)
+    }
+
+    pub async fn add_member(&self, member: WorkspaceMember) -> mongodb::error::Result<()> {
+        self.members.insert_one(member, None).await?;
+        Ok(())
+    }
+
+    pub async fn update_member_role(&self, workspace_id: &str, user_id: &str, role: &str) -> mongodb::error::Result<u64> {
+        let result = self.members.update_one(
+            doc! { "workspace_id": workspace_id, "user_id": user_id },
+            doc! { "$set": { "role": role } },
+            None,
+        ).await?;
+        Ok(result.matched_count)
+    }
+
+    pub async fn remove_member(&self, workspace_id: &str, user_id: &str) -> mongodb::error::Result<u64> {
+        let result = self.members.delete_one(doc! { "workspace_id": workspace_id, "user_id": user_id }, None).await?;
+        Ok(result.deleted_count)
+    }
+
+    // Hapus workspace beserta seluruh membership-nya
+    pub async fn delete(&self, id: &str) -> mongodb::error::Result<()> {
+        let oid = ObjectId::parse_str(id).map_err(|_| mongodb::error::Error::custom("Invalid ID"))?;
+        self.members.delete_many(doc! { "workspace_id": id }, None).await?;
+        self.collection.delete_one(doc! { "_id": oid }, None).await?;
+        Ok(())
+    }
+
+    pub async fn remove_all_memberships(&self, user_id: &str) -> mongodb::error::Result<u64> {
+        let result = self.members.delete_many(doc! { "user_id": user_id }, None).await?;
+        Ok(result.deleted_count)
+    }
+}
@@ -0,0 +1,366 @@
+// Ekstraksi ZIP hasil upload menjadi file-file individual di STAGING_ROOT.
+// Blocking (crate zip butuh Read + Seek), jalankan lewat tokio::task::spawn_blocking.
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::core::file_sniff::{self, FileFormat};
+use crate::services::storage::{new_staging_path, StagedFile};
+
+const MB: u64 = 1024 * 1024;
+
+// Batas anti zip bomb, bisa diubah lewat env ZIP_MAX_ENTRIES, ZIP_MAX_TOTAL_MB, ZIP_MAX_RATIO
+pub struct ArchiveLimits {
+    pub max_entries: usize,
+    pub max_total_bytes: u64, // total ukuran hasil ekstraksi
+    pub max_entry_bytes: u64, // per file, mengikuti batas plan
+    pub max_ratio: u64,       // ukuran asli / ukuran terkompresi per entry
+}
+
+fn env_u64(key: &str, default: u64) -> u64 {
+    env::var(key)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(default)
+}
+
+impl ArchiveLimits {
+    pub fn from_env(max_entry_bytes: u64) -> Self {
+        ArchiveLimits {
+            max_entries: env_u64("ZIP_MAX_ENTRIES", 200) as usize,
+            max_total_bytes: env_u64("ZIP_MAX_TOTAL_MB", 1024) * MB,
+            max_entry_bytes,
+            max_ratio: env_u64("ZIP_MAX_RATIO", 100).max(1),
+        }
+    }
+}
+
+// Hasil per entry: file staging siap di-commit ke storage, atau alasan ditolak
+pub struct ExtractedEntry {
+    pub name: String,
+    pub result: Result<StagedFile, String>,
+}
+
+// Folder, metadata macOS, dan dotfile tidak dianggap dokumen
+fn is_ignored(name: &str) -> bool {
+    name.ends_with('/')
+        || name.starts_with("__MACOSX/")
+        || name.rsplit('/').next().is_some_and(|base| base.starts_with('.'))
+}
+
+// Salin isi entry ke file staging sambil menghitung SHA-256.
+// Berhenti begitu melewati `limit`; byte yang benar-benar keluar yang dihitung, bukan ukuran di header ZIP.
+fn copy_limited(reader: &mut impl Read, dest: &mut File, hasher: &mut Sha256, limit: u64) -> io::Result<u64> {
+    let mut reader = reader.take(limit + 1);
+    let mut buf = [0u8; 64 * 1024];
+    let mut written = 0u64;
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok(written);
+        }
+        written += n as u64;
+        if written > limit {
+            return Ok(written);
+        }
+        hasher.update(&buf[..n]);
+        dest.write_all(&buf[..n])?;
+    }
+}
+
+// Ekstrak satu entry: deteksi format dari byte awal, lalu tulis ke staging.
+// `remaining_total` = sisa jatah ukuran hasil ekstraksi untuk arsip ini.
+fn extract_entry(
+    entry: &mut zip::read::ZipFile<'_>,
+    name: &str,
+    limits: &ArchiveLimits,
+    remaining_total: u64,
+) -> Result<StagedFile, String> {
+    let declared = entry.size();
+    let compressed = entry.compressed_size();
+    if declared > limits.max_entry_bytes {
+        return Err(format!("File too large ({} MB max)", limits.max_entry_bytes / MB));
+    }
+    if compressed > 0 && declared / compressed > limits.max_ratio {
+        return Err(format!("Compression ratio exceeds {}:1", limits.max_ratio));
+    }
+
+    // Header ZIP bisa bohong: batas juga dicek dari byte hasil dekompresi
+    let ratio_limit = compressed.max(1).saturating_mul(limits.max_ratio);
+    let limit = limits.max_entry_bytes.min(remaining_total).min(ratio_limit);
+
+    let mut head = Vec::with_capacity(file_sniff::SNIFF_LEN);
+    entry
+        .by_ref()
+        .take(file_sniff::SNIFF_LEN as u64)
+        .read_to_end(&mut head)
+        .map_err(|e| format!("Corrupt entry: {}", e))?;
+    if head.is_empty() {
+        return Err("Empty file".to_string());
+    }
+
+    let format = file_sniff::detect_and_validate(&head, name).map_err(|e| e.to_string())?;
+    if format == FileFormat::Zip {
+        return Err("Nested archives are not extracted".to_string());
+    }
+
+    let tmp_path = new_staging_path();
+    let mut file = File::create(&tmp_path).map_err(|e| format!("File Error: {}", e))?;
+    let mut hasher = Sha256::new();
+    hasher.update(&head);
+
+    let copied = file
+        .write_all(&head)
+        .and_then(|_| copy_limited(entry, &mut file, &mut hasher, limit.saturating_sub(head.len() as u64)))
+        .and_then(|rest| file.flush().map(|_| head.len() as u64 + rest));
+    drop(file);
+
+    let size_bytes = match copied {
+        Ok(size) if size <= limit => size,
+        Ok(_) => {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(if limit == ratio_limit {
+                format!("Compression ratio exceeds {}:1", limits.max_ratio)
+            } else if limit == remaining_total {
+                format!("Archive exceeds {} MB extracted", limits.max_total_bytes / MB)
+            } else {
+                format!("File too large ({} MB max)", limits.max_entry_bytes / MB)
+            });
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(format!("Corrupt entry: {}", e));
+        }
+    };
+
+    Ok(StagedFile {
+        path: tmp_path,
+        size_bytes,
+        sha256: hex::encode(hasher.finalize()),
+        format,
+    })
+}
+
+// Ekstrak semua dokumen di ZIP. Err = arsip tidak bisa dibaca / terlalu banyak entry;
+// entry yang ditolak tetap dilaporkan satu per satu. Pemanggil wajib commit / hapus file staging.
+pub fn extract_archive(path: &Path, limits: &ArchiveLimits) -> Result<Vec<ExtractedEntry>, String> {
+    let file = File::open(path).map_err(|e| format!("File Error: {}", e))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Invalid ZIP archive: {}", e))?;
+
+    // Nama entry dibaca dari central directory tanpa dekompresi
+    let names: Vec<Option<String>> = (0..archive.len())
+        .map(|i| archive.by_index_raw(i).ok().filter(|e| !e.is_dir()).map(|e| e.name().to_string()))
+        .map(|name| name.filter(|n| !is_ignored(n)))
+        .collect();
+    if names.iter().flatten().count() > limits.max_entries {
+        return Err(format!("Archive has too many files ({} max)", limits.max_entries));
+    }
+
+    let mut entries = Vec::new();
+    let mut total = 0u64;
+    for (i, name) in names.into_iter().enumerate() {
+        let Some(name) = name else { continue };
+        let mut entry = match archive.by_index(i) {
+            Ok(entry) => entry,
+            // Termasuk entry terenkripsi (butuh password)
+            Err(e) => {
+                entries.push(ExtractedEntry { name, result: Err(format!("Unreadable entry: {}", e)) });
+                continue;
+            }
+        };
+
+        let remaining = limits.max_total_bytes.saturating_sub(total);
+        let result = if remaining == 0 {
+            Err(format!("Archive exceeds {} MB extracted", limits.max_total_bytes / MB))
+        } else {
+            extract_entry(&mut entry, &name, limits, remaining)
+        };
+        if let Ok(staged) = &result {
+            total += staged.size_bytes;
+        }
+        entries.push(ExtractedEntry { name, result });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::storage::STAGING_ROOT;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+    use zip::write::FileOptions;
+    use zip::CompressionMethod;
+
+    // ZIP sementara unik per test, dihapus saat di-drop bersama file staging hasil ekstraksi
+    struct TempZip(PathBuf);
+
+    impl TempZip {
+        fn with(name: &str, bytes: &[u8]) -> Self {
+            fs::create_dir_all(STAGING_ROOT).unwrap();
+            let path = env::temp_dir().join(format!("kepin_archive_{}_{}.zip", std::process::id(), name));
+            fs::write(&path, bytes).unwrap();
+            TempZip(path)
+        }
+
+        fn extract(&self, limits: &ArchiveLimits) -> Result<Vec<ExtractedEntry>, String> {
+            let entries = extract_archive(&self.0, limits)?;
+            for staged in entries.iter().filter_map(|e| e.result.as_ref().ok()) {
+                assert!(staged.path.starts_with(STAGING_ROOT));
+                let _ = fs::remove_file(&staged.path);
+            }
+            Ok(entries)
+        }
+    }
+
+    impl Drop for TempZip {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn build_zip(entries: &[(&str, &[u8], CompressionMethod)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content, method) in entries {
+            writer.start_file(*name, FileOptions::default().compression_method(*method)).unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn limits(max_entries: usize, max_total_bytes: u64, max_entry_bytes: u64, max_ratio: u64) -> ArchiveLimits {
+        ArchiveLimits { max_entries, max_total_bytes, max_entry_bytes, max_ratio }
+    }
+
+    fn csv(rows: usize) -> Vec<u8> {
+        let mut content = b"tanggal,keterangan,jumlah\n".to_vec();
+        for i in 0..rows {
+            content.extend_from_slice(format!("2024-01-{:02},item {},{}\n", i % 28 + 1, i, i * 7).as_bytes());
+        }
+        content
+    }
+
+    fn error_of<'a>(entries: &'a [ExtractedEntry], name: &str) -> &'a str {
+        let entry = entries.iter().find(|e| e.name == name).expect("entry not reported");
+        entry.result.as_ref().err().map(String::as_str).expect("entry should be rejected")
+    }
+
+    #[test]
+    fn extracts_documents_and_skips_ignored_entries() {
+        let content = csv(10);
+        let zip = TempZip::with("ok", &build_zip(&[
+            ("laporan/januari.csv", &content, CompressionMethod::Deflated),
+            ("__MACOSX/laporan/._januari.csv", b"meta", CompressionMethod::Stored),
+            ("laporan/.DS_Store", b"meta", CompressionMethod::Stored),
+        ]));
+
+        let entries = zip.extract(&limits(10, MB, MB, 100)).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "laporan/januari.csv");
+        let staged = entries[0].result.as_ref().unwrap();
+        assert_eq!(staged.size_bytes, content.len() as u64);
+        assert_eq!(staged.format, FileFormat::Csv);
+        assert_eq!(staged.sha256, hex::encode(Sha256::digest(&content)));
+    }
+
+    #[test]
+    fn rejects_too_many_entries() {
+        let content = csv(2);
+        let zip = TempZip::with("entries", &build_zip(&[
+            ("a.csv", &content, CompressionMethod::Stored),
+            ("b.csv", &content, CompressionMethod::Stored),
+            ("c.csv", &content, CompressionMethod::Stored),
+            (".hidden.csv", &content, CompressionMethod::Stored),
+        ]));
+
+        let err = zip.extract(&limits(2, MB, MB, 100)).err().unwrap();
+        assert_eq!(err, "Archive has too many files (2 max)");
+        // Dotfile tidak dihitung
+        assert_eq!(zip.extract(&limits(3, MB, MB, 100)).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn rejects_entries_past_total_size() {
+        let content = csv(40);
+        let zip = TempZip::with("total", &build_zip(&[
+            ("a.csv", &content, CompressionMethod::Stored),
+            ("b.csv", &content, CompressionMethod::Stored),
+        ]));
+
+        let total = content.len() as u64 * 3 / 2;
+        let entries = zip.extract(&limits(10, total, MB, 100)).unwrap();
+        assert!(entries[0].result.is_ok());
+        assert!(error_of(&entries, "b.csv").starts_with("Archive exceeds"));
+    }
+
+    #[test]
+    fn rejects_entry_over_size_limit() {
+        let content = csv(200);
+        let zip = TempZip::with("entry_size", &build_zip(&[
+            ("besar.csv", &content, CompressionMethod::Stored),
+            ("kecil.csv", &csv(2), CompressionMethod::Stored),
+        ]));
+
+        let entries = zip.extract(&limits(10, 10 * MB, content.len() as u64 - 1, 100)).unwrap();
+        assert!(error_of(&entries, "besar.csv").starts_with("File too large"));
+        assert!(entries.iter().find(|e| e.name == "kecil.csv").unwrap().result.is_ok());
+    }
+
+    #[test]
+    fn rejects_high_compression_ratio() {
+        // 1 MB baris yang sama berulang terkompresi jadi beberapa KB saja
+        let content = b"a,b,c\n".repeat(MB as usize / 6);
+        let zip = TempZip::with("ratio", &build_zip(&[("bomb.csv", &content, CompressionMethod::Deflated)]));
+
+        let entries = zip.extract(&limits(10, 10 * MB, 10 * MB, 10)).unwrap();
+        assert_eq!(error_of(&entries, "bomb.csv"), "Compression ratio exceeds 10:1");
+    }
+
+    // Ubah ukuran asli di local header & central directory, seolah-olah entry jauh lebih kecil
+    fn forge_uncompressed_size(zip: &mut [u8], size: u32) {
+        let patch = |zip: &mut [u8], signature: &[u8], offset: usize| {
+            let at = zip.windows(4).position(|w| w == signature).unwrap() + offset;
+            zip[at..at + 4].copy_from_slice(&size.to_le_bytes());
+        };
+        patch(zip, b"PK\x03\x04", 22);
+        patch(zip, b"PK\x01\x02", 24);
+    }
+
+    #[test]
+    fn rejects_entry_larger_than_declared() {
+        let content = csv(2000);
+        let mut bytes = build_zip(&[("bohong.csv", &content, CompressionMethod::Deflated)]);
+        forge_uncompressed_size(&mut bytes, 100);
+        let zip = TempZip::with("forged", &bytes);
+
+        // Header lolos cek (100 byte), tapi hasil dekompresi dihitung byte per byte
+        let entries = zip.extract(&limits(10, 10 * MB, 20 * 1024, 1000)).unwrap();
+        assert!(error_of(&entries, "bohong.csv").starts_with("File too large"));
+
+        // Rasio juga dihitung dari byte hasil dekompresi, bukan dari header
+        let entries = zip.extract(&limits(10, 10 * MB, 10 * MB, 2)).unwrap();
+        assert_eq!(error_of(&entries, "bohong.csv"), "Compression ratio exceeds 2:1");
+    }
+
+    #[test]
+    fn nested_archives_and_traversal_names() {
+        let inner = build_zip(&[("dalam.csv", &csv(2), CompressionMethod::Stored)]);
+        let content = csv(2);
+        let zip = TempZip::with("nested", &build_zip(&[
+            ("inner.zip", &inner, CompressionMethod::Stored),
+            ("../../etc/cron.d/evil.csv", &content, CompressionMethod::Stored),
+            ("/abs/path.csv", &content, CompressionMethod::Stored),
+        ]));
+
+        let entries = zip.extract(&limits(10, MB, MB, 100)).unwrap();
+        assert!(entries.iter().find(|e| e.name == "inner.zip").unwrap().result.is_err());
+
+        // Nama entry tidak pernah dipakai sebagai path: file tetap ditulis di STAGING_ROOT (dicek di extract)
+        for name in ["../../etc/cron.d/evil.csv", "/abs/path.csv"] {
+            let staged = entries.iter().find(|e| e.name == name).unwrap().result.as_ref().unwrap();
+            assert_eq!(staged.path.parent(), Some(Path::new(STAGING_ROOT)));
+        }
+    }
+}
@@ -0,0 +1,106 @@
+// Cascade delete upload: record upload, financial reports, dan referensi blob dihapus dalam satu
+// transaksi MongoDB (replica set / mongos). Di server standalone langkah yang sama dijalankan berurutan.
+// File fisik baru dihapus setelah commit; jika gagal, file yatim dibersihkan oleh command `reconcile`.
+use mongodb::error::{TRANSIENT_TRANSACTION_ERROR, UNKNOWN_TRANSACTION_COMMIT_RESULT};
+use mongodb::ClientSession;
+
+use crate::core::media_path;
+use crate::db::AppState;
+use crate::models::upload::UserUpload;
+
+// Percobaan ulang untuk error transaksi sementara (write conflict, failover)
+const MAX_TRANSACTION_ATTEMPTS: u32 = 3;
+
+// Hapus semua record milik upload. None = upload sudah tidak ada (atau sudah di-restore),
+// Some(true) = file fisik tidak dipakai upload lain dan boleh dihapus.
+async fn delete_records(
+    state: &AppState,
+    upload: &UserUpload,
+    id: &str,
+    only_trashed: bool,
+    mut session: Option<&mut ClientSession>,
+) -> mongodb::error::Result<Option<bool>> {
+    if !state.upload_repo.delete_by_id(id, only_trashed, session.as_deref_mut()).await? {
+        return Ok(None);
+    }
+    state.financial_repo.delete_by_upload_id(id, session.as_deref_mut()).await?;
+
+    // Upload lama (tanpa sha256) dan file karantina tidak content-addressed: file selalu milik upload ini saja
+    let release_file = match upload.sha256 {
+        Some(_) if !upload.is_quarantined() => state.media_blob_repo.release_with(&upload.file_path, session).await?,
+        _ => true,
+    };
+    Ok(Some(release_file))
+}
+
+async fn commit_with_retry(session: &mut ClientSession) -> mongodb::error::Result<()> {
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match session.commit_transaction().await {
+            Err(e) if e.contains_label(UNKNOWN_TRANSACTION_COMMIT_RESULT) && attempts < MAX_TRANSACTION_ATTEMPTS => continue,
+            result => return result,
+        }
+    }
+}
+
+async fn delete_records_in_transaction(
+    state: &AppState,
+    upload: &UserUpload,
+    id: &str,
+    only_trashed: bool,
+) -> mongodb::error::Result<Option<bool>> {
+    let mut session = state.mongo_client.start_session(None).await?;
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+        session.start_transaction(None).await?;
+
+        let result = match delete_records(state, upload, id, only_trashed, Some(&mut session)).await {
+            Ok(outcome) => commit_with_retry(&mut session).await.map(|_| outcome),
+            Err(e) => {
+                let _ = session.abort_transaction().await;
+                Err(e)
+            }
+        };
+
+        match result {
+            Err(e) if e.contains_label(TRANSIENT_TRANSACTION_ERROR) && attempts < MAX_TRANSACTION_ATTEMPTS => continue,
+            result => return result,
+        }
+    }
+}
+
+// Hapus permanen satu upload. `only_trashed` = hanya jika upload masih di trash (job purge / hapus dari trash).
+// false = upload sudah tidak ada / sudah di-restore, tidak ada yang dihapus.
+pub async fn delete_upload(state: &AppState, upload: &UserUpload, only_trashed: bool) -> mongodb::error::Result<bool> {
+    let Some(id) = upload.id.map(|oid| oid.to_hex()) else { return Ok(false) };
+
+    let outcome = if state.supports_transactions {
+        delete_records_in_transaction(state, upload, &id, only_trashed).await?
+    } else {
+        delete_records(state, upload, &id, only_trashed, None).await?
+    };
+
+    match outcome {
+        None => Ok(false),
+        Some(release_file) => {
+            if release_file {
+                delete_file(state, &upload.file_path).await;
+            }
+            Ok(true)
+        }
+    }
+}
+
+async fn delete_file(state: &AppState, public_url: &str) {
+    match media_path::public_url_to_key(public_url) {
+        Ok(key) => {
+            if let Err(e) = state.storage.delete(&key).await {
+                eprintln!("Warning: File fisik gagal dihapus (jalankan reconcile): {}", e);
+            }
+        }
+        Err(e) => eprintln!("Warning: Path file tidak valid, file fisik tidak dihapus: {}", e),
+    }
+}
@@ -0,0 +1,199 @@
+use chrono::{Duration, Utc};
+use serde_json::json;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::core::media_path;
+use crate::db::AppState;
+
+pub type ExportError = Box<dyn std::error::Error + Send + Sync>;
+
+// Folder hasil export, sengaja di luar MEDIA_ROOT agar tidak tersaji lewat /public
+pub const EXPORT_ROOT: &str = "exports";
+// Link download berlaku selama ini
+pub const EXPORT_TTL_DAYS: i64 = 7;
+// Export yang masih pending setelah ini dianggap terputus (server crash / restart di tengah export)
+pub const EXPORT_PENDING_TIMEOUT_MINUTES: i64 = 60;
+
+pub fn export_file_path(user_id: &str, export_id: &str) -> PathBuf {
+    Path::new(EXPORT_ROOT).join(user_id).join(format!("{}.zip", export_id))
+}
+
+// Dijalankan di background (tokio::spawn) setelah POST /auth/export
+pub async fn run_export(state: Arc<AppState>, export_id: String, user_id: String) {
+    match build_export(&state, &export_id, &user_id).await {
+        Ok((path, size)) => {
+            if let Err(e) = state.data_export_repo.mark_ready(&export_id, &path, size).await {
+                eprintln!("Export Error: {}", e);
+            }
+            println!("📦 Export {} selesai ({} bytes)", export_id, size);
+        }
+        Err(e) => {
+            eprintln!("Export Error ({}): {}", export_id, e);
+            if let Err(e) = state.data_export_repo.mark_failed(&export_id, &e.to_string()).await {
+                eprintln!("Export Error: {}", e);
+            }
+        }
+    }
+}
+
+// Bersih-bersih berkala (dari job purge trash): export terputus ditandai gagal,
+// file ZIP yang sudah kedaluwarsa dihapus dari EXPORT_ROOT. Kembalikan jumlah file yang dihapus.
+pub async fn cleanup_exports(state: &AppState) -> mongodb::error::Result<usize> {
+    let now = Utc::now();
+    let failed = state.data_export_repo
+        .fail_stale_pending(now - Duration::minutes(EXPORT_PENDING_TIMEOUT_MINUTES))
+        .await?;
+    if failed > 0 {
+        println!("📦 {} export terputus ditandai gagal", failed);
+    }
+
+    let mut removed = 0;
+    for export in state.data_export_repo.find_expired_with_file(now).await? {
+        let (Some(id), Some(path)) = (export.id, export.file_path) else { continue };
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => removed += 1,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => {
+                eprintln!("Export Cleanup Error ({}): {}", path, e);
+                continue;
+            }
+        }
+        state.data_export_repo.clear_file(&id).await?;
+    }
+    Ok(removed)
+}
+
+async fn build_export(state: &AppState, export_id: &str, user_id: &str) -> Result<(String, i64), ExportError> {
+    let user = state.user_repo.find_by_id(user_id).await.ok_or("User not found")?;
+
+    // Password hash, secret TOTP, dan recovery code tidak ikut diekspor
+    let profile = json!({
+        "id": user_id,
+        "name": user.name,
+        "email": user.email,
+        "email_verified": user.email_verified,
+        "plan": user.plan,
+        "role": user.role,
+        "two_factor_enabled": user.totp_enabled,
+        "avatar": user.avatar_url,
+    });
+
+    let uploads = state.upload_repo.find_by_user(user_id).await?;
+    let financial_records = state.financial_repo.find_by_user(user_id).await?;
+
+    let workspaces: Vec<_> = state.workspace_repo.list_for_user(user_id).await?
+        .into_iter()
+        .map(|(ws, role)| json!({
+            "id": ws.id.map(|oid| oid.to_hex()).unwrap_or_default(),
+            "name": ws.name,
+            "role": role,
+            "created_at": ws.created_at,
+        }))
+        .collect();
+
+    let sessions: Vec<_> = state.session_repo.list_active_by_user(user_id).await?
+        .into_iter()
+        .map(|s| json!({
+            "user_agent": s.user_agent,
+            "ip": s.ip,
+            "created_at": s.created_at,
+            "last_used_at": s.last_used_at,
+        }))
+        .collect();
+
+    let api_keys: Vec<_> = state.api_key_repo.list_by_user(user_id).await?
+        .into_iter()
+        .map(|k| json!({
+            "name": k.name,
+            "prefix": k.prefix,
+            "scopes": k.scopes,
+            "revoked": k.revoked,
+            "created_at": k.created_at,
+        }))
+        .collect();
+
+    let entries = vec![
+        ("profile.json", serde_json::to_vec_pretty(&profile)?),
+        ("uploads.json", serde_json::to_vec_pretty(&uploads)?),
+        ("financial_records.json", serde_json::to_vec_pretty(&financial_records)?),
+        ("workspaces.json", serde_json::to_vec_pretty(&workspaces)?),
+        ("sessions.json", serde_json::to_vec_pretty(&sessions)?),
+        ("api_keys.json", serde_json::to_vec_pretty(&api_keys)?),
+    ];
+
+    // File asli diunduh dari storage ke folder sementara, lalu ikut di-zip (file karantina tidak ikut)
+    let out_path = export_file_path(user_id, export_id);
+    let media_dir = Path::new(EXPORT_ROOT).join(user_id).join(format!("{}_files", export_id));
+    let mut keys: Vec<String> = uploads.iter()
+        .filter(|u| !u.is_quarantined())
+        .filter_map(|u| media_path::public_url_to_key(&u.file_path).ok())
+        .chain(user.avatar_url.as_deref().and_then(|url| media_path::public_url_to_key(url).ok()))
+        .collect();
+    keys.sort();
+    keys.dedup();
+
+    let result = async {
+        for key in &keys {
+            // Key = {user_id}/{folder}/{file}; di ZIP menjadi files/{folder}/{file}
+            let relative = key.split_once('/').map(|(_, rest)| rest).unwrap_or(key);
+            if let Err(e) = state.storage.download(key, &media_dir.join(relative)).await {
+                eprintln!("Export Warning: file {} dilewati: {}", key, e);
+            }
+        }
+
+        // Kompresi & baca file = blocking IO
+        let path = out_path.clone();
+        let dir = media_dir.clone();
+        tokio::task::spawn_blocking(move || write_zip(&path, entries, &dir)).await?
+    }.await;
+    let _ = tokio::fs::remove_dir_all(&media_dir).await;
+
+    Ok((out_path.to_string_lossy().to_string(), result? as i64))
+}
+
+fn write_zip(out_path: &Path, entries: Vec<(&str, Vec<u8>)>, media_dir: &Path) -> Result<u64, ExportError> {
+    if let Some(parent) = out_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Tulis ke file sementara dulu, baru di-rename, agar file yang belum selesai tidak bisa diunduh
+    let tmp_path = out_path.with_extension("zip.part");
+    let mut zip = ZipWriter::new(File::create(&tmp_path)?);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for (name, content) in entries {
+        zip.start_file(name, options)?;
+        zip.write_all(&content)?;
+    }
+
+    if media_dir.is_dir() {
+        add_dir(&mut zip, media_dir, media_dir, options)?;
+    }
+
+    zip.finish()?;
+    fs::rename(&tmp_path, out_path)?;
+    Ok(fs::metadata(out_path)?.len())
+}
+
+// File asli (hasil unduhan dari storage) disimpan di dalam folder "files/" pada ZIP
+fn add_dir(zip: &mut ZipWriter<File>, root: &Path, dir: &Path, options: FileOptions) -> Result<(), ExportError> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let path = entry.path();
+
+        if file_type.is_dir() {
+            add_dir(zip, root, &path, options)?;
+        } else if file_type.is_file() {
+            let relative = path.strip_prefix(root)?.to_string_lossy().replace('\\', "/");
+            zip.start_file(format!("files/{}", relative), options)?;
+            io::copy(&mut File::open(&path)?, zip)?;
+        }
+        // Symlink dilewati
+    }
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use axum::async_trait;
+use lettre::{
+    message::header::ContentType,
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use std::{env, path::PathBuf, sync::Arc};
+use chrono::Utc;
+
+pub type MailError = Box<dyn std::error::Error + Send + Sync>;
+
+// Abstraksi pengiriman email, agar SMTP bisa diganti file/stdout saat development & testing
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), MailError>;
+}
+
+// --- SMTP (production) ---
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: String,
+}
+
+impl SmtpMailer {
+    pub fn new(host: &str, port: u16, username: String, password: String, from: String) -> Result<Self, MailError> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?
+            .port(port)
+            .credentials(Credentials::new(username, password))
+            .build();
+        Ok(Self { transport, from })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), MailError> {
+        let message = Message::builder()
+            .from(self.from.parse()?)
+            .to(to.parse()?)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body.to_string())?;
+
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+// --- Stdout (development) ---
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), MailError> {
+        println!("📧 [MAIL] To: {}\nSubject: {}\n\n{}\n", to, subject, body);
+        Ok(())
+    }
+}
+
+// --- File (testing): satu file .txt per email di folder tertentu ---
+pub struct FileMailer {
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), MailError> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let safe_to: String = to.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+        let file_name = format!("{}_{}.txt", Utc::now().format("%Y%m%d_%H%M%S%6f"), safe_to);
+        let content = format!("To: {}\nSubject: {}\n\n{}\n", to, subject, body);
+        tokio::fs::write(self.dir.join(file_name), content).await?;
+        Ok(())
+    }
+}
+
+// Pilih implementasi dari env MAILER = smtp | file | log (default: log)
+pub fn mailer_from_env() -> Arc<dyn Mailer> {
+    match env::var("MAILER").unwrap_or_default().as_str() {
+        "smtp" => {
+            let host = env::var("SMTP_HOST").expect("SMTP_HOST must be set");
+            let port = env::var("SMTP_PORT").ok().and_then(|p| p.parse().ok()).unwrap_or(587);
+            let username = env::var("SMTP_USERNAME").unwrap_or_default();
+            let password = env::var("SMTP_PASSWORD").unwrap_or_default();
+            let from = env::var("MAIL_FROM").unwrap_or_else(|_| "Kepin <no-reply@kepin.local>".to_string());
+            Arc::new(SmtpMailer::new(&host, port, username, password, from).expect("❌ Gagal konfigurasi SMTP"))
+        }
+        "file" => Arc::new(FileMailer::new(env::var("MAIL_DIR").unwrap_or_else(|_| "mail_outbox".to_string()))),
+        _ => Arc::new(LogMailer),
+    }
+}
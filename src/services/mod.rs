@@ -1 +1,10 @@
-pub mod extractor_client;
\ No newline at end of file
+pub mod extractor_client;
+pub mod mailer;
+pub mod oidc;
+pub mod data_export;
+pub mod storage;
+pub mod archive;
+pub mod trash;
+pub mod cascade;
+pub mod reconcile;
+pub mod scanner;
@@ -0,0 +1,161 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::env;
+use tokio::sync::OnceCell;
+
+pub type OidcError = Box<dyn std::error::Error + Send + Sync>;
+
+// Konfigurasi dari env. Endpoint bisa diisi manual (tanpa discovery) agar testing
+// bisa memakai provider tiruan lokal.
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub provider: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub scopes: String,
+    pub auto_provision: bool,
+    pub discovery_url: String,
+    pub authorization_endpoint: Option<String>,
+    pub token_endpoint: Option<String>,
+    pub userinfo_endpoint: Option<String>,
+}
+
+// Bagian dari /.well-known/openid-configuration yang dipakai
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+}
+
+// Klaim standar dari userinfo endpoint
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcUserInfo {
+    pub sub: String,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    pub name: Option<String>,
+}
+
+pub struct OidcClient {
+    pub config: OidcConfig,
+    http: reqwest::Client,
+    metadata: OnceCell<ProviderMetadata>,
+}
+
+impl OidcConfig {
+    // None jika OIDC_ISSUER / OIDC_CLIENT_ID tidak diset (login OIDC nonaktif)
+    pub fn from_env() -> Option<Self> {
+        let issuer = env::var("OIDC_ISSUER").ok()?.trim_end_matches('/').to_string();
+        let client_id = env::var("OIDC_CLIENT_ID").ok()?;
+
+        Some(Self {
+            provider: env::var("OIDC_PROVIDER_NAME").unwrap_or_else(|_| "oidc".to_string()),
+            discovery_url: env::var("OIDC_DISCOVERY_URL")
+                .unwrap_or_else(|_| format!("{}/.well-known/openid-configuration", issuer)),
+            client_id,
+            client_secret: env::var("OIDC_CLIENT_SECRET").ok().filter(|s| !s.is_empty()),
+            redirect_uri: env::var("OIDC_REDIRECT_URI")
+                .unwrap_or_else(|_| "http://localhost:8000/api/v1/auth/oidc/callback".to_string()),
+            scopes: env::var("OIDC_SCOPES").unwrap_or_else(|_| "openid email profile".to_string()),
+            auto_provision: env::var("OIDC_AUTO_PROVISION").map(|v| v != "false").unwrap_or(true),
+            authorization_endpoint: env::var("OIDC_AUTHORIZATION_ENDPOINT").ok(),
+            token_endpoint: env::var("OIDC_TOKEN_ENDPOINT").ok(),
+            userinfo_endpoint: env::var("OIDC_USERINFO_ENDPOINT").ok(),
+        })
+    }
+}
+
+// PKCE (RFC 7636) dengan metode S256
+pub fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+impl OidcClient {
+    pub fn new(config: OidcConfig) -> Self {
+        Self { config, http: reqwest::Client::new(), metadata: OnceCell::new() }
+    }
+
+    // Discovery hanya sekali, lalu di-cache. Endpoint dari env menimpa hasil discovery.
+    pub async fn metadata(&self) -> Result<&ProviderMetadata, OidcError> {
+        self.metadata.get_or_try_init(|| async {
+            let c = &self.config;
+            if let (Some(auth), Some(token), Some(userinfo)) = (&c.authorization_endpoint, &c.token_endpoint, &c.userinfo_endpoint) {
+                return Ok(ProviderMetadata {
+                    authorization_endpoint: auth.clone(),
+                    token_endpoint: token.clone(),
+                    userinfo_endpoint: userinfo.clone(),
+                });
+            }
+
+            let discovered: ProviderMetadata = self.http
+                .get(&c.discovery_url)
+                .send().await?
+                .error_for_status()?
+                .json().await?;
+
+            Ok::<_, OidcError>(ProviderMetadata {
+                authorization_endpoint: c.authorization_endpoint.clone().unwrap_or(discovered.authorization_endpoint),
+                token_endpoint: c.token_endpoint.clone().unwrap_or(discovered.token_endpoint),
+                userinfo_endpoint: c.userinfo_endpoint.clone().unwrap_or(discovered.userinfo_endpoint),
+            })
+        }).await
+    }
+
+    pub async fn authorization_url(&self, state: &str, code_verifier: &str) -> Result<String, OidcError> {
+        let metadata = self.metadata().await?;
+        let url = reqwest::Url::parse_with_params(&metadata.authorization_endpoint, &[
+            ("response_type", "code"),
+            ("client_id", self.config.client_id.as_str()),
+            ("redirect_uri", self.config.redirect_uri.as_str()),
+            ("scope", self.config.scopes.as_str()),
+            ("state", state),
+            ("code_challenge", pkce_challenge(code_verifier).as_str()),
+            ("code_challenge_method", "S256"),
+        ])?;
+        Ok(url.to_string())
+    }
+
+    // Tukar authorization code + code_verifier dengan access token
+    pub async fn exchange_code(&self, code: &str, code_verifier: &str) -> Result<TokenResponse, OidcError> {
+        let metadata = self.metadata().await?;
+        let mut params = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.config.redirect_uri.as_str()),
+            ("client_id", self.config.client_id.as_str()),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(secret) = &self.config.client_secret {
+            params.push(("client_secret", secret.as_str()));
+        }
+
+        let token = self.http
+            .post(&metadata.token_endpoint)
+            .form(&params)
+            .send().await?
+            .error_for_status()?
+            .json().await?;
+        Ok(token)
+    }
+
+    // Identitas diambil dari userinfo endpoint memakai access token dari token endpoint
+    pub async fn userinfo(&self, access_token: &str) -> Result<OidcUserInfo, OidcError> {
+        let metadata = self.metadata().await?;
+        let info = self.http
+            .get(&metadata.userinfo_endpoint)
+            .bearer_auth(access_token)
+            .send().await?
+            .error_for_status()?
+            .json().await?;
+        Ok(info)
+    }
+}
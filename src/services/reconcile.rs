@@ -0,0 +1,192 @@
+// Command maintenance `kepinAPI reconcile [--fix]`: cari data yang tidak konsisten antara storage media,
+// user_uploads, financial_reports, dan media_blobs (sisa cascade delete yang gagal di tengah jalan).
+// Tanpa --fix hanya melaporkan. Record upload yang file-nya hilang hanya dihapus dengan
+// --fix --delete-missing-uploads (ikut menghapus hasil analisa, tidak bisa dibatalkan).
+use chrono::{Duration, Utc};
+use mongodb::bson::oid::ObjectId;
+use mongodb::Database;
+use std::collections::{HashMap, HashSet};
+use std::env;
+
+use crate::core::media_path;
+use crate::models::upload::UserUpload;
+use crate::repository::financial_repo::FinancialRepository;
+use crate::repository::media_blob_repo::MediaBlobRepository;
+use crate::repository::upload_repo::UploadRepository;
+use crate::repository::user_repo::UserRepository;
+use crate::services::storage::{Storage, StorageError};
+
+// File / blob yang lebih baru dari ini dilewati: bisa jadi upload yang sedang berjalan
+// (file sudah di storage, record upload belum dibuat). Env RECONCILE_GRACE_MINUTES.
+fn grace_period() -> Duration {
+    let minutes = env::var("RECONCILE_GRACE_MINUTES")
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .unwrap_or(60);
+    Duration::minutes(minutes)
+}
+
+#[derive(Default)]
+pub struct ReconcileReport {
+    pub orphan_files: Vec<String>,                   // key storage tanpa upload / avatar
+    pub missing_files: Vec<(String, String)>,        // (upload id, file_path) tanpa file fisik
+    pub orphan_financial: Vec<(ObjectId, String)>,   // (record id, id_userupload) tanpa upload induk
+    pub blob_mismatches: Vec<(String, i64, i64)>,    // (file_path, ref_count tercatat, jumlah upload sebenarnya)
+}
+
+impl ReconcileReport {
+    pub fn is_clean(&self) -> bool {
+        self.orphan_files.is_empty()
+            && self.missing_files.is_empty()
+            && self.orphan_financial.is_empty()
+            && self.blob_mismatches.is_empty()
+    }
+
+    pub fn print(&self, fixed: bool, delete_missing: bool) {
+        let action = if fixed { "diperbaiki" } else { "ditemukan" };
+        println!("📋 Reconcile ({}):", action);
+
+        println!("  File tanpa upload: {}", self.orphan_files.len());
+        for key in &self.orphan_files {
+            println!("    - {}", key);
+        }
+        let kept = if fixed && !delete_missing { " (tidak dihapus, pakai --delete-missing-uploads)" } else { "" };
+        println!("  Upload tanpa file fisik: {}{}", self.missing_files.len(), kept);
+        for (id, path) in &self.missing_files {
+            println!("    - {} ({})", id, path);
+        }
+        println!("  Financial report tanpa upload: {}", self.orphan_financial.len());
+        for (id, upload_id) in &self.orphan_financial {
+            println!("    - {} (id_userupload {})", id.to_hex(), upload_id);
+        }
+        println!("  Ref count blob tidak sesuai: {}", self.blob_mismatches.len());
+        for (path, recorded, actual) in &self.blob_mismatches {
+            println!("    - {} (tercatat {}, seharusnya {})", path, recorded, actual);
+        }
+
+        if self.is_clean() {
+            println!("✅ Tidak ada data yatim");
+        } else if !fixed {
+            println!("Jalankan dengan --fix untuk memperbaiki");
+        }
+    }
+}
+
+pub async fn run(db: &Database, storage: &dyn Storage, fix: bool, delete_missing: bool) -> Result<ReconcileReport, StorageError> {
+    let upload_repo = UploadRepository::new(db);
+    let financial_repo = FinancialRepository::new(db);
+    let media_blob_repo = MediaBlobRepository::new(db);
+    let user_repo = UserRepository::new(db);
+
+    let cutoff = Utc::now() - grace_period();
+    let mut report = ReconcileReport::default();
+
+    let files = storage.list().await?;
+    let stored_keys: HashSet<&str> = files.iter().map(|f| f.key.as_str()).collect();
+
+    // 1. Upload yang file fisiknya hilang
+    let uploads = upload_repo.find_all().await?;
+    // Storage kosong padahal ada upload: hampir pasti salah konfigurasi (STORAGE / working directory),
+    // jangan sampai semua upload dianggap hilang
+    if files.is_empty() && !uploads.is_empty() {
+        return Err(format!("Storage listing is empty but {} uploads exist, check STORAGE configuration", uploads.len()).into());
+    }
+    let mut missing_ids = HashSet::new();
+    for upload in uploads.iter().filter(|u| u.created_at < cutoff) {
+        let exists = media_path::public_url_to_key(&upload.file_path)
+            .map(|key| stored_keys.contains(key.as_str()))
+            .unwrap_or(false);
+        if !exists {
+            if let Some(id) = upload.id.map(|oid| oid.to_hex()) {
+                missing_ids.insert(id.clone());
+                report.missing_files.push((id, upload.file_path.clone()));
+            }
+        }
+    }
+    let uploads: Vec<_> = uploads
+        .into_iter()
+        .filter(|u| !u.id.is_some_and(|oid| missing_ids.contains(&oid.to_hex())))
+        .collect();
+
+    // 2. Financial report yang upload induknya sudah tidak ada
+    report.orphan_financial = financial_repo.find_orphans().await?;
+
+    // 3. Ref count blob vs jumlah upload content-addressed yang benar-benar memakai file
+    let mut expected: HashMap<&str, i64> = HashMap::new();
+    // File karantina tidak content-addressed (tidak punya blob)
+    for upload in uploads.iter().filter(|u| u.sha256.is_some() && !u.is_quarantined()) {
+        *expected.entry(upload.file_path.as_str()).or_default() += 1;
+    }
+    let blobs = media_blob_repo.find_all().await?;
+    let recorded: HashMap<&str, i64> = blobs.iter().map(|b| (b.file_path.as_str(), b.ref_count)).collect();
+    for blob in &blobs {
+        let actual = expected.get(blob.file_path.as_str()).copied().unwrap_or(0);
+        if blob.ref_count != actual && blob.created_at < cutoff {
+            report.blob_mismatches.push((blob.file_path.clone(), blob.ref_count, actual));
+        }
+    }
+    for (path, actual) in &expected {
+        if !recorded.contains_key(path) {
+            report.blob_mismatches.push((path.to_string(), 0, *actual));
+        }
+    }
+
+    // 4. File di storage yang tidak dipakai upload maupun avatar
+    let mut referenced: HashSet<String> = uploads.iter().map(|u| u.file_path.clone()).collect();
+    referenced.extend(user_repo.list_all().await?.into_iter().filter_map(|u| u.avatar_url));
+    for file in &files {
+        // Waktu ubah tidak diketahui dianggap baru (tidak dihapus)
+        let is_old = file.last_modified.is_some_and(|at| at < cutoff);
+        if is_old && !referenced.contains(&media_path::public_url_for_key(&file.key)) {
+            report.orphan_files.push(file.key.clone());
+        }
+    }
+
+    if fix {
+        apply_fixes(&report, &uploads, &upload_repo, &financial_repo, &media_blob_repo, storage, delete_missing).await?;
+    }
+    Ok(report)
+}
+
+async fn apply_fixes(
+    report: &ReconcileReport,
+    uploads: &[UserUpload],
+    upload_repo: &UploadRepository,
+    financial_repo: &FinancialRepository,
+    media_blob_repo: &MediaBlobRepository,
+    storage: &dyn Storage,
+    delete_missing: bool,
+) -> Result<(), StorageError> {
+    // Upload tanpa file tidak bisa dianalisa / diunduh lagi: hapus beserta financial report-nya (opt-in)
+    let missing = if delete_missing { report.missing_files.as_slice() } else { &[] };
+    for (id, _) in missing {
+        financial_repo.delete_by_upload_id(id, None).await?;
+        upload_repo.delete_by_id(id, false, None).await?;
+    }
+
+    let orphan_ids: Vec<ObjectId> = report.orphan_financial.iter().map(|(id, _)| *id).collect();
+    if !orphan_ids.is_empty() {
+        financial_repo.delete_by_ids(&orphan_ids).await?;
+    }
+
+    // Blob tanpa upload dihapus (file-nya ikut terhapus di langkah berikut / run berikutnya)
+    for (path, _, actual) in &report.blob_mismatches {
+        if *actual == 0 {
+            media_blob_repo.delete_by_file_path(path).await?;
+            continue;
+        }
+        let Some(upload) = uploads.iter().find(|u| &u.file_path == path) else { continue };
+        media_blob_repo.set_ref_count(
+            path,
+            &upload.user_id,
+            upload.sha256.as_deref().unwrap_or_default(),
+            upload.size_bytes.unwrap_or_default(),
+            *actual,
+        ).await?;
+    }
+
+    for key in &report.orphan_files {
+        storage.delete(key).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,258 @@
+// Pemindaian malware file upload sebelum masuk storage (XLSX bermakro, PDF berbahaya dari pihak luar).
+// File yang terdeteksi tidak dibuang: dipindah ke folder karantina dan upload-nya ditandai,
+// sehingga endpoint analisa & unduh menolaknya.
+use axum::async_trait;
+use std::{env, path::Path, sync::Arc, time::Duration};
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+pub type ScanError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanVerdict {
+    Clean,
+    Infected(String), // nama signature dari scanner
+    Skipped,          // scanner tidak aktif (NoopScanner)
+}
+
+// Abstraksi scanner, agar clamd bisa diganti no-op / scanner palsu saat development & testing
+#[async_trait]
+pub trait Scanner: Send + Sync {
+    async fn scan(&self, path: &Path) -> Result<ScanVerdict, ScanError>;
+}
+
+// --- No-op (default): semua file diterima tanpa dipindai ---
+pub struct NoopScanner;
+
+#[async_trait]
+impl Scanner for NoopScanner {
+    async fn scan(&self, _path: &Path) -> Result<ScanVerdict, ScanError> {
+        Ok(ScanVerdict::Skipped)
+    }
+}
+
+// --- ClamAV (production): perintah INSTREAM ke clamd lewat TCP atau unix socket ---
+pub struct ClamdScanner {
+    addr: String, // "host:port" atau path unix socket ("/run/clamav/clamd.ctl")
+    timeout: Duration,
+}
+
+// Ukuran chunk INSTREAM; total file tetap dibatasi StreamMaxLength di clamd.conf
+const CHUNK_SIZE: usize = 64 * 1024;
+
+impl ClamdScanner {
+    pub fn new(addr: String, timeout: Duration) -> Self {
+        Self { addr, timeout }
+    }
+
+    // Kirim isi file per chunk (panjang 4 byte big-endian + data), diakhiri chunk panjang 0
+    async fn instream<S>(mut stream: S, path: &Path) -> Result<String, ScanError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut file = File::open(path).await?;
+        stream.write_all(b"zINSTREAM\0").await?;
+
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            stream.write_all(&(n as u32).to_be_bytes()).await?;
+            stream.write_all(&buf[..n]).await?;
+        }
+        stream.write_all(&0u32.to_be_bytes()).await?;
+        stream.flush().await?;
+
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).await?;
+        Ok(String::from_utf8_lossy(&reply).trim_end_matches(['\0', '\n']).to_string())
+    }
+
+    async fn send(&self, path: &Path) -> Result<String, ScanError> {
+        #[cfg(unix)]
+        if self.addr.starts_with('/') {
+            let stream = tokio::net::UnixStream::connect(&self.addr).await?;
+            return Self::instream(stream, path).await;
+        }
+        let stream = tokio::net::TcpStream::connect(&self.addr).await?;
+        Self::instream(stream, path).await
+    }
+}
+
+// Balasan clamd: "stream: OK", "stream: {signature} FOUND", atau "{pesan} ERROR"
+fn parse_clamd_reply(reply: &str) -> Result<ScanVerdict, ScanError> {
+    let result = reply.strip_prefix("stream:").unwrap_or(reply).trim();
+    if result == "OK" {
+        Ok(ScanVerdict::Clean)
+    } else if let Some(signature) = result.strip_suffix(" FOUND") {
+        Ok(ScanVerdict::Infected(signature.trim().to_string()))
+    } else {
+        Err(format!("clamd: {}", reply).into())
+    }
+}
+
+#[async_trait]
+impl Scanner for ClamdScanner {
+    async fn scan(&self, path: &Path) -> Result<ScanVerdict, ScanError> {
+        let reply = tokio::time::timeout(self.timeout, self.send(path))
+            .await
+            .map_err(|_| "clamd: timeout")??;
+        parse_clamd_reply(&reply)
+    }
+}
+
+// --- EICAR (testing): hanya menandai file yang berisi string uji EICAR, tanpa clamd ---
+pub struct EicarScanner;
+
+const EICAR: &[u8] = br"X5O!P%@AP[4\PZX54(P^)7CC)7}$EICAR-STANDARD-ANTIVIRUS-TEST-FILE!$H+H*";
+
+#[async_trait]
+impl Scanner for EicarScanner {
+    async fn scan(&self, path: &Path) -> Result<ScanVerdict, ScanError> {
+        let mut file = File::open(path).await?;
+        let mut window: Vec<u8> = Vec::with_capacity(CHUNK_SIZE + EICAR.len());
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                return Ok(ScanVerdict::Clean);
+            }
+            window.extend_from_slice(&buf[..n]);
+            if window.windows(EICAR.len()).any(|w| w == EICAR) {
+                return Ok(ScanVerdict::Infected("Eicar-Test-Signature".to_string()));
+            }
+            // Sisakan ekor agar string yang terpotong di batas chunk tetap terdeteksi
+            let keep = window.len().min(EICAR.len() - 1);
+            window.drain(..window.len() - keep);
+        }
+    }
+}
+
+// Pilih implementasi dari env SCANNER = clamd | eicar | none (default: none)
+pub fn scanner_from_env() -> Arc<dyn Scanner> {
+    match env::var("SCANNER").unwrap_or_default().as_str() {
+        "clamd" => {
+            let addr = env::var("CLAMD_ADDR").unwrap_or_else(|_| "127.0.0.1:3310".to_string());
+            let timeout = env::var("SCANNER_TIMEOUT_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(60);
+            Arc::new(ClamdScanner::new(addr, Duration::from_secs(timeout)))
+        }
+        "eicar" => Arc::new(EicarScanner),
+        _ => Arc::new(NoopScanner),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tokio::io::duplex;
+
+    // File sementara unik per test, dihapus saat di-drop
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        async fn with(name: &str, content: &[u8]) -> Self {
+            let path = env::temp_dir().join(format!("kepin_scan_{}_{}", std::process::id(), name));
+            tokio::fs::write(&path, content).await.unwrap();
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn parses_clamd_replies() {
+        assert_eq!(parse_clamd_reply("stream: OK").unwrap(), ScanVerdict::Clean);
+        assert_eq!(
+            parse_clamd_reply("stream: Win.Test.EICAR_HDB-1 FOUND").unwrap(),
+            ScanVerdict::Infected("Win.Test.EICAR_HDB-1".to_string())
+        );
+        assert!(parse_clamd_reply("stream: Can't allocate memory ERROR").is_err());
+        assert!(parse_clamd_reply("INSTREAM size limit exceeded. ERROR").is_err());
+        assert!(parse_clamd_reply("").is_err());
+    }
+
+    // clamd palsu di ujung lain duplex: baca framing INSTREAM, kembalikan isi file yang diterima
+    async fn fake_clamd(mut server: tokio::io::DuplexStream, reply: &'static [u8]) -> Vec<u8> {
+        let mut command = [0u8; 10];
+        server.read_exact(&mut command).await.unwrap();
+        assert_eq!(&command, b"zINSTREAM\0");
+
+        let mut received = Vec::new();
+        loop {
+            let len = server.read_u32().await.unwrap() as usize;
+            if len == 0 {
+                break;
+            }
+            assert!(len <= CHUNK_SIZE);
+            let mut chunk = vec![0u8; len];
+            server.read_exact(&mut chunk).await.unwrap();
+            received.extend_from_slice(&chunk);
+        }
+        server.write_all(reply).await.unwrap();
+        server.shutdown().await.unwrap();
+        received
+    }
+
+    #[tokio::test]
+    async fn instream_sends_length_prefixed_chunks() {
+        // Lebih dari satu chunk, dengan sisa yang tidak penuh
+        let content: Vec<u8> = (0..CHUNK_SIZE * 2 + 123).map(|i| (i % 251) as u8).collect();
+        let file = TempFile::with("instream.bin", &content).await;
+
+        let (client, server) = duplex(8 * 1024);
+        let clamd = tokio::spawn(fake_clamd(server, b"stream: Eicar-Signature FOUND\0"));
+        let reply = ClamdScanner::instream(client, &file.0).await.unwrap();
+
+        assert_eq!(reply, "stream: Eicar-Signature FOUND");
+        assert_eq!(clamd.await.unwrap(), content);
+        assert_eq!(parse_clamd_reply(&reply).unwrap(), ScanVerdict::Infected("Eicar-Signature".to_string()));
+    }
+
+    #[tokio::test]
+    async fn instream_empty_file_sends_only_terminator() {
+        let file = TempFile::with("empty.bin", b"").await;
+
+        let (client, server) = duplex(1024);
+        let clamd = tokio::spawn(fake_clamd(server, b"stream: OK\0"));
+        let reply = ClamdScanner::instream(client, &file.0).await.unwrap();
+
+        assert_eq!(parse_clamd_reply(&reply).unwrap(), ScanVerdict::Clean);
+        assert!(clamd.await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn clamd_unreachable_is_error() {
+        let file = TempFile::with("unreachable.csv", b"a,b\n").await;
+        let scanner = ClamdScanner::new("127.0.0.1:1".to_string(), Duration::from_secs(5));
+        assert!(scanner.scan(&file.0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn eicar_detected_across_chunk_boundary() {
+        // Signature terpotong: separuh di akhir chunk pertama, sisanya di chunk kedua
+        let split = EICAR.len() / 2;
+        let mut content = vec![b'a'; CHUNK_SIZE - split];
+        content.extend_from_slice(EICAR);
+        content.extend_from_slice(b"\n1,2\n");
+        let file = TempFile::with("eicar_split.csv", &content).await;
+
+        assert_eq!(
+            EicarScanner.scan(&file.0).await.unwrap(),
+            ScanVerdict::Infected("Eicar-Test-Signature".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn eicar_clean_file_passes() {
+        let file = TempFile::with("clean.csv", &vec![b'x'; CHUNK_SIZE * 2]).await;
+        assert_eq!(EicarScanner.scan(&file.0).await.unwrap(), ScanVerdict::Clean);
+        assert_eq!(NoopScanner.scan(&file.0).await.unwrap(), ScanVerdict::Skipped);
+    }
+}
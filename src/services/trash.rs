@@ -0,0 +1,73 @@
+// Trash upload: upload yang dihapus (soft delete) di-purge permanen setelah masa retensi
+use chrono::{DateTime, Duration, Utc};
+use std::env;
+use std::sync::Arc;
+
+use crate::db::AppState;
+use crate::services::{cascade, data_export};
+
+// Jumlah upload yang di-purge per putaran query
+const PURGE_BATCH: i64 = 100;
+
+fn env_i64(key: &str, default: i64) -> i64 {
+    env::var(key)
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(default)
+}
+
+// Masa simpan di trash, env TRASH_RETENTION_DAYS (default 30 hari)
+pub fn retention_days() -> i64 {
+    env_i64("TRASH_RETENTION_DAYS", 30)
+}
+
+// Waktu upload di trash akan dihapus permanen oleh job purge
+pub fn purge_at(deleted_at: DateTime<Utc>) -> DateTime<Utc> {
+    deleted_at + Duration::days(retention_days())
+}
+
+// Hapus permanen semua upload yang masa retensinya habis, kembalikan jumlahnya
+pub async fn purge_expired(state: &AppState) -> mongodb::error::Result<usize> {
+    let cutoff = Utc::now() - Duration::days(retention_days());
+    let mut purged = 0;
+
+    loop {
+        let uploads = state.upload_repo.find_deleted_before(cutoff, PURGE_BATCH).await?;
+        let fetched = uploads.len();
+        let mut progressed = false;
+
+        for upload in &uploads {
+            if cascade::delete_upload(state, upload, true).await? {
+                purged += 1;
+                progressed = true;
+            }
+        }
+        // Batch tidak penuh = sudah habis; tanpa progres = hindari loop tak berujung
+        if fetched < PURGE_BATCH as usize || !progressed {
+            return Ok(purged);
+        }
+    }
+}
+
+// Dijalankan di background (tokio::spawn) saat server start,
+// interval dari env TRASH_PURGE_INTERVAL_MINUTES (default 60 menit).
+// Putaran yang sama membersihkan export data akun yang terputus / kedaluwarsa.
+pub async fn run_purge_job(state: Arc<AppState>) {
+    let every = std::time::Duration::from_secs(env_i64("TRASH_PURGE_INTERVAL_MINUTES", 60) as u64 * 60);
+    let mut interval = tokio::time::interval(every);
+
+    loop {
+        interval.tick().await;
+        match purge_expired(&state).await {
+            Ok(0) => {}
+            Ok(count) => println!("🗑️ Trash purge: {} upload dihapus permanen", count),
+            Err(e) => eprintln!("Trash Purge Error: {}", e),
+        }
+        match data_export::cleanup_exports(&state).await {
+            Ok(0) => {}
+            Ok(count) => println!("📦 Export cleanup: {} file kedaluwarsa dihapus", count),
+            Err(e) => eprintln!("Export Cleanup Error: {}", e),
+        }
+    }
+}